@@ -0,0 +1,621 @@
+use std::cell::RefCell;
+use std::fmt::{self, Debug, Formatter};
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+pub struct BatchBySizeIterator<I: Iterator, F> {
+    iter: Peekable<I>,
+    max_size: usize,
+    size_of: F,
+}
+
+impl<I, F> Debug for BatchBySizeIterator<I, F>
+where
+    I: Iterator + Debug,
+    I::Item: Debug,
+    F: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatchBySizeIterator")
+            .field("iter", &self.iter)
+            .field("max_size", &self.max_size)
+            .field("size_of", &self.size_of)
+            .finish()
+    }
+}
+
+impl<I, F> Clone for BatchBySizeIterator<I, F>
+where
+    I: Iterator + Clone,
+    I::Item: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+            max_size: self.max_size,
+            size_of: self.size_of.clone(),
+        }
+    }
+}
+
+impl<I: Iterator, F> Iterator for BatchBySizeIterator<I, F>
+where
+    F: Fn(&I::Item) -> usize,
+{
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut batch = vec![];
+        let mut batch_size = 0;
+
+        while let Some(item) = self.iter.peek() {
+            let size = (self.size_of)(item);
+            // An item too big for any batch still gets a batch of its own
+            if !batch.is_empty() && batch_size + size > self.max_size {
+                break;
+            }
+
+            batch_size += size;
+            batch.extend(self.iter.next());
+        }
+
+        if batch.is_empty() {
+            None
+        } else {
+            Some(batch)
+        }
+    }
+}
+
+pub trait IntoBatchBySizeIterator: IntoIterator {
+    /// Groups items into batches whose total size doesn't exceed the maximum.
+    /// Items larger than the maximum are yielded in a batch by themselves.
+    fn batch_by_size<F>(self, max_size: usize, size_of: F) -> BatchBySizeIterator<Self::IntoIter, F>
+    where
+        Self: Sized,
+        F: Fn(&Self::Item) -> usize,
+    {
+        BatchBySizeIterator {
+            iter: self.into_iter().peekable(),
+            max_size,
+            size_of,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoBatchBySizeIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct FramesIterator<I> {
+    iter: I,
+    size: usize,
+    remainder: Vec<u8>,
+}
+
+impl<I> FramesIterator<I> {
+    /// The leftover bytes at the end of the stream that didn't fill a whole frame.
+    /// Only populated once the iterator has run out.
+    pub fn remainder(&self) -> &[u8] {
+        &self.remainder
+    }
+}
+
+impl<I: Iterator<Item = u8>> Iterator for FramesIterator<I> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.iter.by_ref().take(self.size).collect::<Vec<_>>();
+        if frame.len() == self.size {
+            return Some(frame);
+        }
+
+        if !frame.is_empty() {
+            self.remainder = frame;
+        }
+        None
+    }
+}
+
+pub trait IntoFramesIterator: IntoIterator<Item = u8> {
+    /// Splits a stream of bytes into frames of exactly the given size.
+    /// Trailing bytes that don't make a full frame are kept aside, see FramesIterator::remainder.
+    /// Panics if the size is 0.
+    fn frames(self, size: usize) -> FramesIterator<Self::IntoIter>
+    where
+        Self: Sized,
+    {
+        assert!(size > 0, "frame size must be non-zero");
+        FramesIterator {
+            iter: self.into_iter(),
+            size,
+            remainder: vec![],
+        }
+    }
+}
+
+impl<I: IntoIterator<Item = u8>> IntoFramesIterator for I {}
+
+#[derive(Debug)]
+pub struct ChunkByTimeIterator<T> {
+    _handle: JoinHandle<()>,
+    receiver: Receiver<T>,
+    window: Duration,
+    done: bool,
+}
+
+impl<T> Iterator for ChunkByTimeIterator<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut batch = vec![];
+        while !self.done && batch.is_empty() {
+            let deadline = Instant::now() + self.window;
+            loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                match self.receiver.recv_timeout(remaining) {
+                    Ok(item) => batch.push(item),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => {
+                        self.done = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        if batch.is_empty() {
+            None
+        } else {
+            Some(batch)
+        }
+    }
+}
+
+pub trait IntoChunkByTimeIterator: IntoIterator {
+    /// Pulls items on a background thread, and groups together the ones that arrive within each
+    /// window of time. Windows start when the next batch is requested, and windows where nothing
+    /// arrives are skipped. The last batch is yielded as soon as the source runs out.
+    /// Items are queued without bound until they're batched, so a slow consumer uses more memory.
+    fn chunk_by_time(self, window: Duration) -> ChunkByTimeIterator<Self::Item>
+    where
+        Self: Sized,
+        Self::IntoIter: Send + 'static,
+        Self::Item: Send + 'static,
+    {
+        let iter = self.into_iter();
+        let (sender, receiver) = channel();
+        let handle = thread::spawn(move || {
+            for item in iter {
+                if sender.send(item).is_err() {
+                    break;
+                }
+            }
+        });
+
+        ChunkByTimeIterator {
+            _handle: handle,
+            receiver,
+            window,
+            done: false,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoChunkByTimeIterator for I {}
+
+pub struct ChunkByKeyCappedIterator<I: Iterator, F> {
+    iter: Peekable<I>,
+    key: F,
+    max: usize,
+}
+
+impl<I, F> Debug for ChunkByKeyCappedIterator<I, F>
+where
+    I: Iterator + Debug,
+    I::Item: Debug,
+    F: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChunkByKeyCappedIterator")
+            .field("iter", &self.iter)
+            .field("key", &self.key)
+            .field("max", &self.max)
+            .finish()
+    }
+}
+
+impl<I, F> Clone for ChunkByKeyCappedIterator<I, F>
+where
+    I: Iterator + Clone,
+    I::Item: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+            key: self.key.clone(),
+            max: self.max,
+        }
+    }
+}
+
+impl<I: Iterator, F, K> Iterator for ChunkByKeyCappedIterator<I, F>
+where
+    F: Fn(&I::Item) -> K,
+    K: PartialEq,
+{
+    type Item = (K, Vec<I::Item>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        let key = (self.key)(&item);
+
+        let mut chunk = vec![item];
+        while chunk.len() < self.max {
+            match self.iter.next_if(|x| (self.key)(x) == key) {
+                Some(item) => chunk.push(item),
+                None => break,
+            }
+        }
+
+        Some((key, chunk))
+    }
+}
+
+pub trait IntoChunkByKeyCappedIterator: IntoIterator {
+    /// Groups runs of consecutive items with the same key into chunks, yielded with their key.
+    /// Runs longer than max are split into several chunks with the same key.
+    fn chunk_by_key_capped<K, F>(
+        self,
+        key: F,
+        max: usize,
+    ) -> ChunkByKeyCappedIterator<Self::IntoIter, F>
+    where
+        Self: Sized,
+        F: Fn(&Self::Item) -> K,
+        K: PartialEq,
+    {
+        assert!(max > 0, "chunks must be able to hold at least one item");
+        ChunkByKeyCappedIterator {
+            iter: self.into_iter().peekable(),
+            key,
+            max,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoChunkByKeyCappedIterator for I {}
+
+#[derive(Debug, Clone)]
+struct GroupRunsState<I: Iterator, F, K> {
+    iter: I,
+    key: F,
+    /// An item pulled from the source which hasn't been yielded yet, with its key
+    peeked: Option<(K, I::Item)>,
+    /// The key of the current group, and its id
+    current: Option<K>,
+    group_id: usize,
+}
+
+impl<I, F, K> GroupRunsState<I, F, K>
+where
+    I: Iterator,
+    F: Fn(&I::Item) -> K,
+    K: PartialEq,
+{
+    /// The next item, if it's part of the current group.
+    fn next_in_group(&mut self) -> Option<I::Item> {
+        let (key, item) = match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => {
+                let item = self.iter.next()?;
+                ((self.key)(&item), item)
+            }
+        };
+
+        if self.current.as_ref() == Some(&key) {
+            Some(item)
+        } else {
+            self.peeked = Some((key, item));
+            None
+        }
+    }
+}
+
+pub struct GroupRunsIterator<I: Iterator, F, K> {
+    state: Rc<RefCell<GroupRunsState<I, F, K>>>,
+}
+
+impl<I, F, K> Debug for GroupRunsIterator<I, F, K>
+where
+    I: Iterator + Debug,
+    I::Item: Debug,
+    F: Debug,
+    K: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GroupRunsIterator")
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+/// Clones get a copy of the source rather than sharing it, so groups already handed out stay
+/// tied to the original.
+impl<I, F, K> Clone for GroupRunsIterator<I, F, K>
+where
+    I: Iterator + Clone,
+    I::Item: Clone,
+    F: Clone,
+    K: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(self.state.borrow().clone())),
+        }
+    }
+}
+
+impl<I, F, K> Iterator for GroupRunsIterator<I, F, K>
+where
+    I: Iterator,
+    F: Fn(&I::Item) -> K,
+    K: PartialEq + Clone,
+{
+    type Item = (K, Group<I, F, K>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut state = self.state.borrow_mut();
+
+        // Skip whatever's left of the previous group
+        while state.next_in_group().is_some() {}
+
+        let (key, item) = match state.peeked.take() {
+            Some(peeked) => peeked,
+            None => {
+                let item = state.iter.next()?;
+                ((state.key)(&item), item)
+            }
+        };
+        state.current = Some(key.clone());
+        state.peeked = Some((key.clone(), item));
+        state.group_id += 1;
+
+        let group = Group {
+            state: Rc::clone(&self.state),
+            id: state.group_id,
+        };
+        Some((key, group))
+    }
+}
+
+/// The items in one run of group_runs, pulled straight from the source.
+pub struct Group<I: Iterator, F, K> {
+    state: Rc<RefCell<GroupRunsState<I, F, K>>>,
+    id: usize,
+}
+
+impl<I, F, K> Debug for Group<I, F, K>
+where
+    I: Iterator + Debug,
+    I::Item: Debug,
+    F: Debug,
+    K: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Group")
+            .field("state", &self.state)
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+/// Like GroupRunsIterator, clones get a copy of the source, so they can be consumed without
+/// moving the original on.
+impl<I, F, K> Clone for Group<I, F, K>
+where
+    I: Iterator + Clone,
+    I::Item: Clone,
+    F: Clone,
+    K: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(self.state.borrow().clone())),
+            id: self.id,
+        }
+    }
+}
+
+impl<I, F, K> Iterator for Group<I, F, K>
+where
+    I: Iterator,
+    F: Fn(&I::Item) -> K,
+    K: PartialEq,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut state = self.state.borrow_mut();
+        // The outer iterator has already moved on from this group
+        if state.group_id != self.id {
+            return None;
+        }
+
+        state.next_in_group()
+    }
+}
+
+pub trait IntoGroupRunsIterator: IntoIterator {
+    /// Groups runs of consecutive items with the same key, yielding each run lazily as an
+    /// iterator alongside its key, so runs don't need to be collected into memory.
+    /// The groups share the source, so each group should be consumed before moving on to the next.
+    /// Any items left in a group are skipped once the next one is pulled.
+    fn group_runs<K, F>(self, key: F) -> GroupRunsIterator<Self::IntoIter, F, K>
+    where
+        Self: Sized,
+        F: Fn(&Self::Item) -> K,
+        K: PartialEq + Clone,
+    {
+        GroupRunsIterator {
+            state: Rc::new(RefCell::new(GroupRunsState {
+                iter: self.into_iter(),
+                key,
+                peeked: None,
+                current: None,
+                group_id: 0,
+            })),
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoGroupRunsIterator for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        IntoBatchBySizeIterator, IntoChunkByKeyCappedIterator, IntoChunkByTimeIterator,
+        IntoFramesIterator, IntoGroupRunsIterator,
+    };
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    #[test]
+    fn test_batch_by_size() {
+        let words = ["ab", "cde", "f", "ghijklmnop", "qr", "s"];
+        let batches = words
+            .iter()
+            .batch_by_size(5, |x| x.len())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            batches,
+            [
+                vec![&"ab", &"cde"],
+                vec![&"f"],
+                vec![&"ghijklmnop"],
+                vec![&"qr", &"s"]
+            ]
+        );
+
+        // Clones carry on from the same place, including the peeked item
+        let size_of: fn(&&str) -> usize = |x| x.len();
+        let mut batches = words.into_iter().batch_by_size(5, size_of);
+        batches.next();
+        assert_eq!(batches.clone().count(), 3);
+        assert!(format!("{:?}", batches).starts_with("BatchBySizeIterator"));
+    }
+
+    #[test]
+    fn test_batch_by_size_empty() {
+        let batches = Vec::<String>::new()
+            .into_iter()
+            .batch_by_size(5, |x| x.len())
+            .count();
+        assert_eq!(batches, 0);
+    }
+
+    #[test]
+    fn test_frames() {
+        let mut frames = (0..10_u8).frames(4);
+        assert_eq!(frames.next(), Some(vec![0, 1, 2, 3]));
+        assert_eq!(frames.next(), Some(vec![4, 5, 6, 7]));
+        assert_eq!(frames.next(), None);
+        assert_eq!(frames.remainder(), [8, 9]);
+
+        let mut frames = (0..8_u8).frames(4);
+        assert_eq!(frames.by_ref().count(), 2);
+        assert!(frames.remainder().is_empty());
+    }
+
+    #[test]
+    fn test_chunk_by_time() {
+        let (items, source) = channel();
+        let mut batches = source.into_iter().chunk_by_time(Duration::from_millis(500));
+
+        // Everything queued before the window runs out goes in the batch
+        for x in 0..3 {
+            items.send(x).unwrap();
+        }
+        assert_eq!(batches.next(), Some(vec![0, 1, 2]));
+
+        // The last batch ends as soon as the source does
+        for x in 3..6 {
+            items.send(x).unwrap();
+        }
+        drop(items);
+        assert_eq!(batches.next(), Some(vec![3, 4, 5]));
+        assert_eq!(batches.next(), None);
+    }
+
+    #[test]
+    fn test_chunk_by_key_capped() {
+        let chunks = (0..25)
+            .chunk_by_key_capped(|x| x / 10, 3)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            chunks,
+            [
+                (0, vec![0, 1, 2]),
+                (0, vec![3, 4, 5]),
+                (0, vec![6, 7, 8]),
+                (0, vec![9]),
+                (1, vec![10, 11, 12]),
+                (1, vec![13, 14, 15]),
+                (1, vec![16, 17, 18]),
+                (1, vec![19]),
+                (2, vec![20, 21, 22]),
+                (2, vec![23, 24]),
+            ]
+        );
+
+        let chunks = [1, 1, 2, 1].chunk_by_key_capped(|x| *x, 10);
+        assert_eq!(
+            chunks.collect::<Vec<_>>(),
+            [(1, vec![1, 1]), (2, vec![2]), (1, vec![1])]
+        );
+
+        // Clones carry on from the same place, including the peeked item
+        let key: fn(&i32) -> i32 = |x| *x;
+        let mut chunks = [1, 1, 2, 2].chunk_by_key_capped(key, 10);
+        chunks.next();
+        assert_eq!(chunks.clone().collect::<Vec<_>>(), [(2, vec![2, 2])]);
+        assert!(format!("{:?}", chunks).starts_with("ChunkByKeyCappedIterator"));
+    }
+
+    #[test]
+    fn test_group_runs() {
+        let sums = [1, 1, 2, 2, 2, 1, 3]
+            .group_runs(|x| *x)
+            .map(|(key, group)| (key, group.sum::<i32>()))
+            .collect::<Vec<_>>();
+        assert_eq!(sums, [(1, 2), (2, 6), (1, 1), (3, 3)]);
+
+        // Partly consumed and stale groups
+        let mut groups = (0..9).group_runs(|x| x / 3);
+        let (_, mut first) = groups.next().unwrap();
+        assert_eq!(first.next(), Some(0));
+        let (key, second) = groups.next().unwrap();
+        assert_eq!(first.next(), None);
+        assert_eq!((key, second.collect::<Vec<_>>()), (1, vec![3, 4, 5]));
+        assert_eq!(groups.map(|(key, _)| key).collect::<Vec<_>>(), [2]);
+
+        // Clones have their own copy of the source
+        let key: fn(&i32) -> i32 = |x| x / 3;
+        let mut groups = (0..9).group_runs(key);
+        let (_, mut first) = groups.next().unwrap();
+        first.next();
+        assert_eq!(first.clone().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(first.next(), Some(1));
+        let keys = groups.clone().map(|(key, _)| key).collect::<Vec<_>>();
+        assert_eq!(keys, [1, 2]);
+        assert_eq!(groups.next().unwrap().1.collect::<Vec<_>>(), [3, 4, 5]);
+        assert!(format!("{:?}", groups).starts_with("GroupRunsIterator"));
+        assert!(format!("{:?}", first).starts_with("Group {"));
+    }
+}
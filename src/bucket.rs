@@ -1,3 +1,32 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Either<L, R> {
+    Left(L),
+    Right(R),
+}
+
+/// Finds the bin a value falls into, given sorted bin edges. Each bin includes its lower edge,
+/// and the last one includes its upper edge too. Values outside of the edges give the nearest
+/// bin as an error, and values which can't be compared to the edges at all (eg. NaN) give None.
+fn bin_index<T: PartialOrd>(edges: &[T], value: &T) -> Result<usize, Option<usize>> {
+    assert!(edges.len() >= 2, "need at least 2 edges to make a bin");
+    let last_bin = edges.len() - 2;
+
+    if value.partial_cmp(&edges[0]).is_none() {
+        return Err(None);
+    }
+
+    match edges.partition_point(|edge| edge <= value) {
+        0 => Err(Some(0)),
+        n if n == edges.len() && *value <= edges[last_bin + 1] => Ok(last_bin),
+        n if n == edges.len() => Err(Some(last_bin)),
+        n => Ok(n - 1),
+    }
+}
+
 pub trait Bucket {
     /// Partition the items of this iterator into several buckets based on a bucketing function
     /// The bucketing function must map each item to its associated bucket index.
@@ -17,13 +46,243 @@ pub trait Bucket {
 
         buckets
     }
+
+    /// Distribute the items round-robin between n shards, eg. to split work between consumers.
+    /// Like bucket, all of the items are collected up front.
+    fn shard(self, n: usize) -> Vec<std::vec::IntoIter<Self::Item>>
+    where
+        Self: Iterator + Sized,
+    {
+        assert!(n > 0, "can't shard into 0 shards");
+        let mut shards = (0..n).map(|_| vec![]).collect::<Vec<_>>();
+
+        for (index, item) in self.enumerate() {
+            shards[index % n].push(item);
+        }
+
+        shards.into_iter().map(Vec::into_iter).collect()
+    }
+
+    /// Like bucket, but with a fixed number of buckets known at compile time.
+    /// Instead of panicking, returns the offending index if the bucketing function gives one
+    /// that's out of range.
+    fn try_bucket_arr<F, const N: usize>(
+        self,
+        partition_func: F,
+    ) -> Result<[Vec<Self::Item>; N], usize>
+    where
+        Self: Iterator + Sized,
+        F: Fn(&Self::Item) -> usize,
+    {
+        let mut buckets = std::array::from_fn(|_| vec![]);
+
+        for item in self {
+            let index = partition_func(&item);
+            buckets.get_mut(index).ok_or(index)?.push(item);
+        }
+
+        Ok(buckets)
+    }
+
+    /// Sort the items into histogram bins, based on where their value falls between the given
+    /// sorted edges. n edges make n - 1 bins. Values outside of the edges go to the first or last
+    /// bin. Panics on values which can't be compared to the edges, eg. NaN.
+    fn bin_by_edges<T, F>(self, edges: &[T], value: F) -> Vec<Vec<Self::Item>>
+    where
+        Self: Iterator + Sized,
+        T: PartialOrd,
+        F: Fn(&Self::Item) -> T,
+    {
+        let mut bins = (1..edges.len()).map(|_| vec![]).collect::<Vec<_>>();
+
+        for item in self {
+            let index = match bin_index(edges, &value(&item)) {
+                Ok(index) | Err(Some(index)) => index,
+                Err(None) => panic!("value can't be compared to the bin edges"),
+            };
+            bins[index].push(item);
+        }
+
+        bins
+    }
+
+    /// Like bin_by_edges, but returns the first item which falls outside of the edges instead of
+    /// putting it in the nearest bin. Values which can't be compared to the edges, eg. NaN, are
+    /// returned the same way.
+    fn try_bin_by_edges<T, F>(
+        self,
+        edges: &[T],
+        value: F,
+    ) -> Result<Vec<Vec<Self::Item>>, Self::Item>
+    where
+        Self: Iterator + Sized,
+        T: PartialOrd,
+        F: Fn(&Self::Item) -> T,
+    {
+        let mut bins = (1..edges.len()).map(|_| vec![]).collect::<Vec<_>>();
+
+        for item in self {
+            match bin_index(edges, &value(&item)) {
+                Ok(index) => bins[index].push(item),
+                Err(_) => return Err(item),
+            }
+        }
+
+        Ok(bins)
+    }
+
+    /// Partition the items of this iterator into two vectors of different types.
+    /// The partition function maps each item to either a left or right value.
+    fn partition_map<F, L, R>(self, partition_func: F) -> (Vec<L>, Vec<R>)
+    where
+        Self: Iterator + Sized,
+        F: Fn(Self::Item) -> Either<L, R>,
+    {
+        let mut left = vec![];
+        let mut right = vec![];
+
+        for item in self {
+            match partition_func(item) {
+                Either::Left(l) => left.push(l),
+                Either::Right(r) => right.push(r),
+            }
+        }
+
+        (left, right)
+    }
+
+    /// Count how many items map to each key, without storing the items themselves.
+    fn count_by<K, F>(self, key_func: F) -> HashMap<K, usize>
+    where
+        Self: Iterator + Sized,
+        K: Hash + Eq,
+        F: Fn(&Self::Item) -> K,
+    {
+        self.count_by_with_hasher::<K, F, RandomState>(key_func)
+    }
+
+    /// Like count_by, but using the given hasher for the map.
+    fn count_by_with_hasher<K, F, S>(self, key_func: F) -> HashMap<K, usize, S>
+    where
+        Self: Iterator + Sized,
+        K: Hash + Eq,
+        F: Fn(&Self::Item) -> K,
+        S: BuildHasher + Default,
+    {
+        let mut counts = HashMap::default();
+
+        for item in self {
+            *counts.entry(key_func(&item)).or_insert(0) += 1;
+        }
+
+        counts
+    }
+
+    /// Group items by a key, storing a value derived from each item rather than the item itself.
+    fn group_map<K, V, FK, FV>(self, key_func: FK, value_func: FV) -> HashMap<K, Vec<V>>
+    where
+        Self: Iterator + Sized,
+        K: Hash + Eq,
+        FK: Fn(&Self::Item) -> K,
+        FV: Fn(Self::Item) -> V,
+    {
+        self.group_map_with_hasher::<K, V, FK, FV, RandomState>(key_func, value_func)
+    }
+
+    /// Like group_map, but using the given hasher for the map.
+    fn group_map_with_hasher<K, V, FK, FV, S>(
+        self,
+        key_func: FK,
+        value_func: FV,
+    ) -> HashMap<K, Vec<V>, S>
+    where
+        Self: Iterator + Sized,
+        K: Hash + Eq,
+        FK: Fn(&Self::Item) -> K,
+        FV: Fn(Self::Item) -> V,
+        S: BuildHasher + Default,
+    {
+        let mut groups = HashMap::<K, Vec<V>, S>::default();
+
+        for item in self {
+            groups
+                .entry(key_func(&item))
+                .or_default()
+                .push(value_func(item));
+        }
+
+        groups
+    }
+
+    /// Aggregate a value from each item per key, without collecting the groups first. Each key's
+    /// accumulator starts as a clone of init, and is updated with fold.
+    fn fold_by_key<K, V, A, FK, FV, FF>(
+        self,
+        key_func: FK,
+        value_func: FV,
+        init: A,
+        fold: FF,
+    ) -> HashMap<K, A>
+    where
+        Self: Iterator + Sized,
+        K: Hash + Eq,
+        A: Clone,
+        FK: Fn(&Self::Item) -> K,
+        FV: Fn(Self::Item) -> V,
+        FF: Fn(&mut A, V),
+    {
+        let mut groups = HashMap::new();
+
+        for item in self {
+            let acc = groups
+                .entry(key_func(&item))
+                .or_insert_with(|| init.clone());
+            fold(acc, value_func(item));
+        }
+
+        groups
+    }
+
+    /// Split a stream of results into its values and errors, appending them to the given vectors.
+    /// This lets several streams share the same accumulators without reallocating.
+    fn bucket_result_into_existing<T, E>(self, oks: &mut Vec<T>, errs: &mut Vec<E>)
+    where
+        Self: Iterator<Item = Result<T, E>> + Sized,
+    {
+        for item in self {
+            match item {
+                Ok(value) => oks.push(value),
+                Err(err) => errs.push(err),
+            }
+        }
+    }
 }
 
 impl<T: Iterator + Sized> Bucket for T {}
 
 #[cfg(test)]
 mod tests {
-    use super::Bucket;
+    use super::{Bucket, Either};
+    use std::collections::HashMap;
+    use std::hash::{BuildHasherDefault, Hasher};
+
+    /// A deliberately simple hasher, to check that custom hashers are used.
+    #[derive(Default)]
+    struct TrivialHasher(u64);
+
+    impl Hasher for TrivialHasher {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for b in bytes {
+                self.0 = self.0.wrapping_mul(31).wrapping_add(*b as u64);
+            }
+        }
+    }
+
+    type TrivialState = BuildHasherDefault<TrivialHasher>;
 
     #[test]
     fn test_bucket() {
@@ -46,4 +305,123 @@ mod tests {
         let buckets = items.into_iter().bucket(3, bucket_func);
         println!("{:?}", buckets);
     }
+
+    #[test]
+    fn test_shard() {
+        let shards = (0..9)
+            .shard(3)
+            .into_iter()
+            .map(|shard| shard.collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(shards, [[0, 3, 6], [1, 4, 7], [2, 5, 8]]);
+
+        let shards = (0..2).shard(3);
+        assert_eq!(
+            shards.into_iter().map(|s| s.len()).collect::<Vec<_>>(),
+            [1, 1, 0]
+        );
+    }
+
+    #[test]
+    fn test_try_bucket_arr() {
+        let buckets = (0..6).try_bucket_arr::<_, 3>(|x| x % 3);
+        assert_eq!(buckets, Ok([vec![0, 3], vec![1, 4], vec![2, 5]]));
+
+        let buckets = (0..6).try_bucket_arr::<_, 3>(|x| *x);
+        assert_eq!(buckets, Err(3));
+    }
+
+    #[test]
+    fn test_bin_by_edges() {
+        let edges = [0.0, 1.0, 2.0];
+        let values = [0.5, 1.0, 1.5, 2.0, -1.0, 3.0];
+
+        let bins = values.into_iter().bin_by_edges(&edges, |x| *x);
+        assert_eq!(bins, [vec![0.5, -1.0], vec![1.0, 1.5, 2.0, 3.0]]);
+
+        let bins = values[..4].iter().try_bin_by_edges(&edges, |x| **x);
+        assert_eq!(bins, Ok(vec![vec![&0.5], vec![&1.0, &1.5, &2.0]]));
+
+        let bins = values.into_iter().try_bin_by_edges(&edges, |x| *x);
+        assert_eq!(bins, Err(-1.0));
+
+        let bins = [0.5, f64::NAN].into_iter().try_bin_by_edges(&edges, |x| *x);
+        assert!(bins.is_err_and(|x| x.is_nan()));
+    }
+
+    #[test]
+    #[should_panic(expected = "can't be compared")]
+    fn test_bin_by_edges_nan() {
+        [0.5, f64::NAN]
+            .into_iter()
+            .bin_by_edges(&[0.0, 1.0], |x| *x);
+    }
+
+    #[test]
+    fn test_partition_map() {
+        let items = ["1.5", "abc", "2", "def"];
+        let (words, numbers): (Vec<String>, Vec<f64>) =
+            items.into_iter().partition_map(|x| match x.parse::<f64>() {
+                Ok(n) => Either::Right(n),
+                Err(_) => Either::Left(x.to_string()),
+            });
+        assert_eq!(words, ["abc", "def"]);
+        assert_eq!(numbers, [1.5, 2.0]);
+    }
+
+    #[test]
+    fn test_count_by() {
+        let counts = "hello world".chars().count_by(|c| *c);
+        assert_eq!(counts[&'l'], 3);
+        assert_eq!(counts[&'o'], 2);
+        assert_eq!(counts[&'h'], 1);
+        assert_eq!(counts.len(), 8);
+    }
+
+    #[test]
+    fn test_group_map() {
+        let items = [(1, "a"), (1, "b"), (2, "c")];
+        let groups = items.into_iter().group_map(|(id, _)| *id, |(_, x)| x);
+        assert_eq!(groups, HashMap::from([(1, vec!["a", "b"]), (2, vec!["c"])]));
+    }
+
+    #[test]
+    fn test_fold_by_key() {
+        let items = [(1, 10), (2, 5), (1, 20)];
+        let totals =
+            items
+                .into_iter()
+                .fold_by_key(|(id, _)| *id, |(_, x)| x, 0, |total, x| *total += x);
+        assert_eq!(totals, HashMap::from([(1, 30), (2, 5)]));
+    }
+
+    #[test]
+    fn test_with_hasher() {
+        let counts = "hello world"
+            .chars()
+            .count_by_with_hasher::<_, _, TrivialState>(|c| *c);
+        let expected = "hello world".chars().count_by(|c| *c);
+        assert_eq!(counts.into_iter().collect::<HashMap<_, _>>(), expected);
+
+        let items = [(1, "a"), (1, "b"), (2, "c")];
+        let groups = items
+            .into_iter()
+            .group_map_with_hasher::<_, _, _, _, TrivialState>(|(id, _)| *id, |(_, x)| x);
+        let expected = items.into_iter().group_map(|(id, _)| *id, |(_, x)| x);
+        assert_eq!(groups.into_iter().collect::<HashMap<_, _>>(), expected);
+    }
+
+    #[test]
+    fn test_bucket_result_into_existing() {
+        let (mut oks, mut errs) = (vec![], vec![]);
+        [Ok(1), Err("a"), Ok(2)]
+            .into_iter()
+            .bucket_result_into_existing(&mut oks, &mut errs);
+        [Err("b"), Ok(3)]
+            .into_iter()
+            .bucket_result_into_existing(&mut oks, &mut errs);
+
+        assert_eq!(oks, [1, 2, 3]);
+        assert_eq!(errs, ["a", "b"]);
+    }
 }
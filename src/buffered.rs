@@ -1,10 +1,17 @@
 use std::collections::VecDeque;
+use std::panic::resume_unwind;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BufferedIterator<I: Iterator> {
     inner: I,
-    buffer: VecDeque<Option<I::Item>>,
+    buffer: VecDeque<I::Item>,
     max_capacity: usize,
+    exhausted: bool,
+    /// When set, the buffer is only refilled once drained, and grows toward this limit each time.
+    growth_limit: Option<usize>,
+    filled: bool,
 }
 
 impl<I: Iterator> BufferedIterator<I> {
@@ -13,8 +20,35 @@ impl<I: Iterator> BufferedIterator<I> {
             inner: iter,
             buffer: VecDeque::with_capacity(capacity),
             max_capacity: capacity,
+            exhausted: false,
+            growth_limit: None,
+            filled: false,
         }
     }
+
+    /// Creates a buffer which starts at the initial capacity and doubles, up to the max, each
+    /// time the consumer drains it.
+    pub fn adaptive(iter: I, initial: usize, max: usize) -> BufferedIterator<I> {
+        Self {
+            growth_limit: Some(max.max(initial)),
+            ..Self::new(iter, initial)
+        }
+    }
+
+    /// The maximum number of items held in the buffer.
+    pub fn capacity(&self) -> usize {
+        self.max_capacity
+    }
+
+    /// The number of items currently in the buffer.
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Whether the buffer currently holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
 }
 
 impl<T, I: Iterator<Item=T>> Iterator for BufferedIterator<I>
@@ -22,21 +56,152 @@ impl<T, I: Iterator<Item=T>> Iterator for BufferedIterator<I>
     type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.fill_buffer();
-        self.buffer.pop_front().unwrap()
+        match self.growth_limit {
+            Some(limit) => if self.buffer.is_empty() {
+                if self.filled {
+                    self.max_capacity = (self.max_capacity * 2).max(1).min(limit);
+                }
+                self.fill_buffer();
+                self.filled = true;
+            },
+            None => self.fill_buffer(),
+        }
+        self.buffer.pop_front()
     }
 }
 
 impl<T, I: Iterator<Item=T>> BufferedIterator<I> {
+    /// Fills the buffer up to capacity, or until the inner iterator runs out.
+    /// A capacity of 0 still pulls one item at a time, passing items straight through.
     fn fill_buffer(&mut self) {
-        while self.buffer.len() < self.max_capacity {
-            let val = self.inner.next();
-            self.buffer.push_back(val);
-            if self.buffer[self.buffer.len() - 1].is_none() { return; }
+        while !self.exhausted && self.buffer.len() < self.max_capacity.max(1) {
+            match self.inner.next() {
+                Some(val) => self.buffer.push_back(val),
+                None => self.exhausted = true,
+            }
         }
     }
 }
 
+/// What a prefetching producer does when the buffer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferPolicy {
+    /// Wait for the consumer to make space.
+    Block,
+    /// Make space by dropping the oldest buffered item, for when stale items are worse than lost ones.
+    DropOldest,
+    /// Drop the new item, keeping what's already buffered.
+    DropNewest,
+}
+
+#[derive(Debug)]
+struct PrefetchQueue<T> {
+    items: VecDeque<T>,
+    /// Set by the producer once it has finished, including by panicking
+    done: bool,
+    /// Set by the consumer once it has been dropped
+    closed: bool,
+}
+
+type SharedQueue<T> = Arc<(Mutex<PrefetchQueue<T>>, Condvar)>;
+
+/// Marks the queue as done when the producer finishes, even if the inner iterator panics.
+struct DoneGuard<T>(SharedQueue<T>);
+
+impl<T> Drop for DoneGuard<T> {
+    fn drop(&mut self) {
+        let (queue, condvar) = &*self.0;
+        queue.lock().unwrap_or_else(|e| e.into_inner()).done = true;
+        condvar.notify_all();
+    }
+}
+
+/// Buffers items from an iterator which runs on a background thread.
+#[derive(Debug)]
+pub struct PrefetchIterator<T> {
+    shared: SharedQueue<T>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl<T: Send + 'static> PrefetchIterator<T> {
+    pub fn new<I>(iter: I, capacity: usize, policy: BufferPolicy) -> PrefetchIterator<T>
+        where
+            I: Iterator<Item=T> + Send + 'static
+    {
+        let capacity = capacity.max(1);
+        let shared: SharedQueue<T> = Arc::new((
+            Mutex::new(PrefetchQueue {
+                items: VecDeque::with_capacity(capacity),
+                done: false,
+                closed: false,
+            }),
+            Condvar::new(),
+        ));
+
+        let guard = DoneGuard(Arc::clone(&shared));
+        let handle = thread::spawn(move || {
+            let (queue, condvar) = &*guard.0;
+            for item in iter {
+                let mut queue = queue.lock().unwrap();
+                if policy == BufferPolicy::Block {
+                    queue = condvar.wait_while(queue, |q| q.items.len() >= capacity && !q.closed).unwrap();
+                }
+                if queue.closed {
+                    break;
+                }
+
+                if queue.items.len() >= capacity {
+                    match policy {
+                        BufferPolicy::DropOldest => { queue.items.pop_front(); }
+                        BufferPolicy::DropNewest => continue,
+                        BufferPolicy::Block => unreachable!(),
+                    }
+                }
+                queue.items.push_back(item);
+                condvar.notify_all();
+            }
+        });
+
+        Self {
+            shared,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl<T> Iterator for PrefetchIterator<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (queue, condvar) = &*self.shared;
+        let item = {
+            let queue = queue.lock().unwrap();
+            let mut queue = condvar.wait_while(queue, |q| q.items.is_empty() && !q.done).unwrap();
+            let item = queue.items.pop_front();
+            condvar.notify_all();
+            item
+        };
+
+        if item.is_none() {
+            // The producer has finished, so check whether it ended by panicking
+            if let Some(Err(payload)) = self.handle.take().map(JoinHandle::join) {
+                resume_unwind(payload);
+            }
+        }
+
+        item
+    }
+}
+
+impl<T> Drop for PrefetchIterator<T> {
+    fn drop(&mut self) {
+        // Lets a blocked producer stop early
+        let (queue, condvar) = &*self.shared;
+        queue.lock().unwrap_or_else(|e| e.into_inner()).closed = true;
+        condvar.notify_all();
+    }
+}
+
 pub trait IntoBufferedIterator {
     /// Creates a buffered iterator with the given capacity.
     fn buffered(self, capacity: usize) -> BufferedIterator<Self>
@@ -45,6 +210,46 @@ pub trait IntoBufferedIterator {
     {
         BufferedIterator::new(self, capacity)
     }
+
+    /// Creates a buffered iterator over fallible items with the given capacity.
+    /// Errors are buffered like any other item, so prefetching carries on past them - only the
+    /// end of the inner iterator stops it.
+    fn buffered_results<T, E>(self, capacity: usize) -> BufferedIterator<Self>
+        where
+            Self: Sized + Iterator<Item=Result<T, E>>
+    {
+        BufferedIterator::new(self, capacity)
+    }
+
+    /// Creates a buffered iterator which starts small for low latency, and grows its capacity
+    /// toward the max as the consumer keeps up.
+    fn buffered_adaptive(self, initial: usize, max: usize) -> BufferedIterator<Self>
+        where
+            Self: Sized + Iterator
+    {
+        BufferedIterator::adaptive(self, initial, max)
+    }
+
+    /// Creates a buffered iterator which pulls from this one on a background thread, so items are
+    /// prepared while the consumer is busy. If the iterator panics, the panic is passed on to the
+    /// consumer once the items before it have been yielded.
+    fn prefetch(self, capacity: usize) -> PrefetchIterator<Self::Item>
+        where
+            Self: Sized + Iterator + Send + 'static,
+            Self::Item: Send + 'static
+    {
+        PrefetchIterator::new(self, capacity, BufferPolicy::Block)
+    }
+
+    /// Like prefetch, but with a choice of what the producer does when the buffer is full,
+    /// rather than always waiting for the consumer.
+    fn prefetch_with_policy(self, capacity: usize, policy: BufferPolicy) -> PrefetchIterator<Self::Item>
+        where
+            Self: Sized + Iterator + Send + 'static,
+            Self::Item: Send + 'static
+    {
+        PrefetchIterator::new(self, capacity, policy)
+    }
 }
 
 impl<I: Iterator> IntoBufferedIterator for I {}
@@ -52,7 +257,12 @@ impl<I: Iterator> IntoBufferedIterator for I {}
 
 #[cfg(test)]
 mod tests {
-    use crate::buffered::IntoBufferedIterator;
+    use crate::buffered::{BufferPolicy, IntoBufferedIterator};
+    use std::cell::Cell;
+    use std::iter;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::sync::mpsc::{channel, RecvTimeoutError};
+    use std::time::Duration;
 
     #[test]
     fn test_iter() {
@@ -61,4 +271,146 @@ mod tests {
             .collect::<Vec<_>>();
         assert_eq!(iter, (0..10).collect::<Vec<_>>())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_capacities() {
+        for capacity in [0, 1, 3, 10, 20] {
+            let iter = (0..10).buffered(capacity).collect::<Vec<_>>();
+            assert_eq!(iter, (0..10).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_exhausted() {
+        let mut iter = (0..2).buffered(4);
+        assert_eq!(iter.next(), Some(0));
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_len() {
+        let mut iter = (0..10).buffered(4);
+        assert_eq!(iter.len(), 0);
+        iter.next();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.capacity(), 4);
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut iter = (0..10).buffered(4);
+        iter.next();
+        iter.next();
+
+        let cloned = iter.clone();
+        assert_eq!(iter.next(), Some(2));
+        assert_eq!(cloned.collect::<Vec<_>>(), (2..10).collect::<Vec<_>>());
+        assert_eq!(iter.collect::<Vec<_>>(), (3..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_results() {
+        let pulled = Cell::new(0);
+        let source = [Ok(1), Err("bad"), Ok(2)]
+            .into_iter()
+            .inspect(|_| pulled.set(pulled.get() + 1));
+
+        let mut iter = source.buffered_results(3);
+        assert_eq!(iter.next(), Some(Ok(1)));
+        assert_eq!(pulled.get(), 3);
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.collect::<Vec<_>>(), [Err("bad"), Ok(2)]);
+    }
+
+    #[test]
+    fn test_adaptive() {
+        let pulled = Cell::new(0);
+        let mut iter = (0..100)
+            .inspect(|_| pulled.set(pulled.get() + 1))
+            .buffered_adaptive(2, 8);
+
+        assert_eq!(iter.next(), Some(0));
+        assert_eq!((iter.capacity(), pulled.get()), (2, 2));
+        iter.next();
+        assert_eq!(iter.next(), Some(2));
+        assert_eq!((iter.capacity(), pulled.get()), (4, 6));
+
+        for _ in 0..3 {
+            iter.next();
+        }
+        iter.next();
+        assert_eq!((iter.capacity(), pulled.get()), (8, 14));
+
+        // Capped at the max
+        for _ in 0..8 {
+            iter.next();
+        }
+        assert_eq!((iter.capacity(), pulled.get()), (8, 22));
+
+        assert_eq!(iter.collect::<Vec<_>>(), (15..100).collect::<Vec<_>>());
+        assert_eq!((0..10).buffered_adaptive(0, 0).count(), 10);
+    }
+
+    #[test]
+    fn test_prefetch() {
+        let items = (0..100).prefetch(8).collect::<Vec<_>>();
+        assert_eq!(items, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_prefetch_panic() {
+        // Panics on the 5th item
+        let source = (0..10).inspect(|x| {
+            if *x == 4 {
+                panic!("bad item {x}");
+            }
+        });
+
+        let mut seen = vec![];
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            for x in source.prefetch(2) {
+                seen.push(x);
+            }
+        }));
+
+        let payload = result.expect_err("the consumer should panic");
+        assert_eq!(payload.downcast_ref::<String>().unwrap(), "bad item 4");
+        assert_eq!(seen, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_prefetch_policy() {
+        // The producer runs to the end before the consumer starts, filling the buffer
+        let after_producer = |policy| {
+            let (finished, wait_finished) = channel();
+            let source = (0..100).chain(iter::from_fn(move || {
+                let _ = finished.send(());
+                None
+            }));
+            let iter = source.prefetch_with_policy(5, policy);
+            wait_finished.recv().unwrap();
+            iter.collect::<Vec<_>>()
+        };
+
+        assert_eq!(after_producer(BufferPolicy::DropOldest), [95, 96, 97, 98, 99]);
+        assert_eq!(after_producer(BufferPolicy::DropNewest), [0, 1, 2, 3, 4]);
+        // A blocked producer can't finish before the consumer makes space, and nothing is lost
+        let blocked = (0..100).prefetch_with_policy(5, BufferPolicy::Block);
+        assert_eq!(blocked.collect::<Vec<_>>(), (0..100).collect::<Vec<_>>());
+
+        // Dropping the consumer early stops a blocked producer, which hangs up the channel as it
+        // drops the source
+        let (alive, producer) = channel::<()>();
+        let mut iter = (0..)
+            .inspect(move |_| {
+                let _alive = &alive;
+            })
+            .prefetch(2);
+        assert_eq!(iter.next(), Some(0));
+        drop(iter);
+        let stopped = producer.recv_timeout(Duration::from_secs(10));
+        assert_eq!(stopped, Err(RecvTimeoutError::Disconnected));
+    }
+}
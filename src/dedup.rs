@@ -0,0 +1,280 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt::{self, Debug, Formatter};
+use std::hash::Hash;
+use std::iter::Peekable;
+
+pub struct DedupWithCountIterator<I: Iterator> {
+    iter: Peekable<I>,
+}
+
+impl<I> Debug for DedupWithCountIterator<I>
+where
+    I: Iterator + Debug,
+    I::Item: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DedupWithCountIterator")
+            .field("iter", &self.iter)
+            .finish()
+    }
+}
+
+impl<I> Clone for DedupWithCountIterator<I>
+where
+    I: Iterator + Clone,
+    I::Item: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+        }
+    }
+}
+
+impl<I> Iterator for DedupWithCountIterator<I>
+where
+    I: Iterator,
+    I::Item: PartialEq,
+{
+    type Item = (usize, I::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        let mut count = 1;
+        while self.iter.next_if_eq(&item).is_some() {
+            count += 1;
+        }
+
+        Some((count, item))
+    }
+}
+
+pub trait IntoDedupWithCountIterator: IntoIterator {
+    /// Collapses runs of equal items into one, along with the length of the run.
+    fn dedup_with_count(self) -> DedupWithCountIterator<Self::IntoIter>
+    where
+        Self: Sized,
+        Self::Item: PartialEq,
+    {
+        DedupWithCountIterator {
+            iter: self.into_iter().peekable(),
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoDedupWithCountIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct TakeUntilRepeatIterator<I, F, K> {
+    iter: I,
+    key: F,
+    seen: HashSet<K>,
+    done: bool,
+}
+
+impl<I, F, K> Iterator for TakeUntilRepeatIterator<I, F, K>
+where
+    I: Iterator,
+    F: Fn(&I::Item) -> K,
+    K: Hash + Eq,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let item = self.iter.next()?;
+        if self.seen.insert((self.key)(&item)) {
+            Some(item)
+        } else {
+            // The repeated item is dropped
+            self.done = true;
+            None
+        }
+    }
+}
+
+pub trait IntoTakeUntilRepeatIterator: IntoIterator {
+    /// Yields items until one has a key that was already seen. The repeated item is not yielded.
+    fn take_until_repeat<K, F>(self, key: F) -> TakeUntilRepeatIterator<Self::IntoIter, F, K>
+    where
+        Self: Sized,
+        F: Fn(&Self::Item) -> K,
+        K: Hash + Eq,
+    {
+        TakeUntilRepeatIterator {
+            iter: self.into_iter(),
+            key,
+            seen: HashSet::new(),
+            done: false,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoTakeUntilRepeatIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct DedupWindowIterator<I: Iterator> {
+    iter: I,
+    window: usize,
+    recent: VecDeque<I::Item>,
+    seen: HashSet<I::Item>,
+}
+
+impl<I> Iterator for DedupWindowIterator<I>
+where
+    I: Iterator,
+    I::Item: Hash + Eq + Clone,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.find(|item| !self.seen.contains(item))?;
+        if self.window > 0 {
+            // Items in the window are never yielded again, so they're all unique
+            if self.recent.len() == self.window {
+                let oldest = self.recent.pop_front().unwrap();
+                self.seen.remove(&oldest);
+            }
+            self.recent.push_back(item.clone());
+            self.seen.insert(item.clone());
+        }
+
+        Some(item)
+    }
+}
+
+pub trait IntoDedupWindowIterator: IntoIterator {
+    /// Skips items which are equal to one of the last `window` items yielded.
+    /// Only the window is held in memory, so an item can be yielded again once it has left it.
+    fn dedup_window(self, window: usize) -> DedupWindowIterator<Self::IntoIter>
+    where
+        Self: Sized,
+        Self::Item: Hash + Eq + Clone,
+    {
+        DedupWindowIterator {
+            iter: self.into_iter(),
+            window,
+            recent: VecDeque::with_capacity(window),
+            seen: HashSet::with_capacity(window),
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoDedupWindowIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct CoalesceIterator<I: Iterator, F> {
+    iter: I,
+    func: F,
+    last: Option<I::Item>,
+}
+
+impl<I, F> Iterator for CoalesceIterator<I, F>
+where
+    I: Iterator,
+    F: FnMut(I::Item, I::Item) -> Result<I::Item, (I::Item, I::Item)>,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut last = self.last.take().or_else(|| self.iter.next())?;
+        for item in self.iter.by_ref() {
+            match (self.func)(last, item) {
+                Ok(merged) => last = merged,
+                Err((done, item)) => {
+                    self.last = Some(item);
+                    return Some(done);
+                }
+            }
+        }
+
+        Some(last)
+    }
+}
+
+pub trait IntoCoalesceIterator: IntoIterator {
+    /// Merges adjacent items with the given function. It returns Ok with the merged item, which
+    /// may then be merged with the next one, or Err with both items to keep them separate.
+    fn coalesce<F>(self, func: F) -> CoalesceIterator<Self::IntoIter, F>
+    where
+        Self: Sized,
+        F: FnMut(Self::Item, Self::Item) -> Result<Self::Item, (Self::Item, Self::Item)>,
+    {
+        CoalesceIterator {
+            iter: self.into_iter(),
+            func,
+            last: None,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoCoalesceIterator for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        IntoCoalesceIterator, IntoDedupWindowIterator, IntoDedupWithCountIterator,
+        IntoTakeUntilRepeatIterator,
+    };
+
+    #[test]
+    fn test_dedup_with_count() {
+        let runs = ["a", "a", "a", "b", "c", "c"]
+            .dedup_with_count()
+            .collect::<Vec<_>>();
+        assert_eq!(runs, [(3, "a"), (1, "b"), (2, "c")]);
+
+        // Clones carry on from the same place, including the peeked item
+        let mut runs = [1, 1, 2, 2, 2].dedup_with_count();
+        runs.next();
+        assert_eq!(runs.clone().collect::<Vec<_>>(), [(3, 2)]);
+        assert!(format!("{:?}", runs).starts_with("DedupWithCountIterator"));
+
+        assert_eq!(Vec::<i32>::new().dedup_with_count().count(), 0);
+    }
+
+    #[test]
+    fn test_take_until_repeat() {
+        let mut iter = ["a", "b", "c", "a", "d", "e"].take_until_repeat(|x| *x);
+        assert_eq!(iter.by_ref().collect::<Vec<_>>(), ["a", "b", "c"]);
+        assert_eq!(iter.next(), None);
+
+        let records = [(1, "x"), (2, "y"), (2, "z")];
+        let first = records.take_until_repeat(|(id, _)| *id).count();
+        assert_eq!(first, 2);
+
+        assert_eq!((0..5).take_until_repeat(|x| *x).count(), 5);
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let sums = [1, 2, -1, -3, -2, 4, 0, -5]
+            .coalesce(|a, b| {
+                if (a < 0) == (b < 0) {
+                    Ok(a + b)
+                } else {
+                    Err((a, b))
+                }
+            })
+            .collect::<Vec<i32>>();
+        assert_eq!(sums, [3, -6, 4, -5]);
+
+        let items = Vec::<i32>::new().coalesce(|a, b| Ok(a + b));
+        assert_eq!(items.count(), 0);
+    }
+
+    #[test]
+    fn test_dedup_window() {
+        let items = [1, 2, 1, 3, 1].dedup_window(2).collect::<Vec<_>>();
+        assert_eq!(items, [1, 2, 3, 1]);
+
+        let items = [1, 1, 2, 2].dedup_window(0).collect::<Vec<_>>();
+        assert_eq!(items, [1, 1, 2, 2]);
+
+        let items = [1, 2, 3, 1, 2, 3].dedup_window(10).collect::<Vec<_>>();
+        assert_eq!(items, [1, 2, 3]);
+    }
+}
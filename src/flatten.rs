@@ -0,0 +1,67 @@
+use std::iter::Flatten;
+
+#[derive(Debug, Clone)]
+pub struct FlattenOptionsCountedIterator<I> {
+    iter: I,
+    dropped: usize,
+}
+
+impl<I> FlattenOptionsCountedIterator<I> {
+    /// The number of Nones skipped so far.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+impl<T, I: Iterator<Item = Option<T>>> Iterator for FlattenOptionsCountedIterator<I> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for item in self.iter.by_ref() {
+            match item {
+                Some(value) => return Some(value),
+                None => self.dropped += 1,
+            }
+        }
+
+        None
+    }
+}
+
+pub trait IntoFlattenOptionsIterator: IntoIterator {
+    /// Yields the values of the Some items, skipping the Nones.
+    fn flatten_options<T>(self) -> Flatten<Self::IntoIter>
+    where
+        Self: Sized + IntoIterator<Item = Option<T>>,
+    {
+        self.into_iter().flatten()
+    }
+
+    /// Like flatten_options, but keeps count of the Nones skipped.
+    fn flatten_options_counted<T>(self) -> FlattenOptionsCountedIterator<Self::IntoIter>
+    where
+        Self: Sized + IntoIterator<Item = Option<T>>,
+    {
+        FlattenOptionsCountedIterator {
+            iter: self.into_iter(),
+            dropped: 0,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoFlattenOptionsIterator for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::IntoFlattenOptionsIterator;
+
+    #[test]
+    fn test_flatten_options() {
+        let items = [Some(1), None, Some(2)];
+        assert_eq!(items.flatten_options().collect::<Vec<_>>(), [1, 2]);
+
+        let mut iter = items.flatten_options_counted();
+        assert_eq!(iter.by_ref().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(iter.dropped(), 1);
+    }
+}
@@ -1,5 +1,12 @@
-#[derive(Debug)]
-struct InterleaveIterator<I, J> {
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt::{self, Debug, Formatter};
+use std::iter::{Fuse, Peekable};
+
+use crate::bucket::Either;
+
+#[derive(Debug, Clone)]
+pub struct InterleaveIterator<I, J> {
     left: I,
     right: J,
     next_left: bool,
@@ -22,10 +29,320 @@ impl<L, R> Iterator for InterleaveIterator<L, R>
 
         item
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Whichever side is next gets one more item than the other before it runs out
+        let length = |left: usize, right: usize| match self.next_left {
+            true => left.saturating_mul(2).min(right.saturating_mul(2).saturating_add(1)),
+            false => right.saturating_mul(2).min(left.saturating_mul(2).saturating_add(1)),
+        };
+
+        let (left_lower, left_upper) = self.left.size_hint();
+        let (right_lower, right_upper) = self.right.size_hint();
+        let upper = match (left_upper, right_upper) {
+            (Some(left), Some(right)) => Some(length(left, right)),
+            (Some(left), None) => Some(length(left, usize::MAX)),
+            (None, Some(right)) => Some(length(usize::MAX, right)),
+            (None, None) => None,
+        };
+
+        (length(left_lower, right_lower), upper)
+    }
+}
+
+impl<L, R> ExactSizeIterator for InterleaveIterator<L, R>
+    where
+        L: ExactSizeIterator,
+        R: ExactSizeIterator<Item=L::Item>
+{}
+
+#[derive(Debug, Clone)]
+pub struct InterleaveThenRestIterator<I, J> {
+    left: Fuse<I>,
+    right: Fuse<J>,
+    next_left: bool,
+}
+
+impl<L, R> Iterator for InterleaveThenRestIterator<L, R>
+where
+    L: Iterator,
+    R: Iterator<Item = L::Item>,
+{
+    type Item = L::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = match self.next_left {
+            true => self.left.next().or_else(|| self.right.next()),
+            false => self.right.next().or_else(|| self.left.next()),
+        };
+
+        self.next_left = !self.next_left;
+
+        item
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InterleaveEitherIterator<I, J> {
+    left: I,
+    right: J,
+    next_left: bool,
+}
+
+impl<L, R> Iterator for InterleaveEitherIterator<L, R>
+where
+    L: Iterator,
+    R: Iterator,
+{
+    type Item = Either<L::Item, R::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = match self.next_left {
+            true => self.left.next().map(Either::Left),
+            false => self.right.next().map(Either::Right),
+        };
+
+        self.next_left = !self.next_left;
+
+        item
+    }
+}
+
+pub struct InterleavePadIterator<I: Iterator, J: Iterator, F> {
+    left: Fuse<I>,
+    right: Peekable<Fuse<J>>,
+    pad: F,
+    next_left: bool,
+}
+
+// Peekable needs the items to be Debug/Clone too, which derive doesn't ask for
+impl<I, J, F> Debug for InterleavePadIterator<I, J, F>
+where
+    I: Iterator + Debug,
+    J: Iterator + Debug,
+    J::Item: Debug,
+    F: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InterleavePadIterator")
+            .field("left", &self.left)
+            .field("right", &self.right)
+            .field("pad", &self.pad)
+            .field("next_left", &self.next_left)
+            .finish()
+    }
+}
+
+impl<I, J, F> Clone for InterleavePadIterator<I, J, F>
+where
+    I: Iterator + Clone,
+    J: Iterator + Clone,
+    J::Item: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            left: self.left.clone(),
+            right: self.right.clone(),
+            pad: self.pad.clone(),
+            next_left: self.next_left,
+        }
+    }
+}
+
+impl<L, R, F> Iterator for InterleavePadIterator<L, R, F>
+where
+    L: Iterator,
+    R: Iterator<Item = L::Item>,
+    F: FnMut() -> L::Item,
+{
+    type Item = L::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = match self.next_left {
+            true => match self.left.next() {
+                Some(item) => item,
+                // Only start a new pair if there's something left on the right
+                None => {
+                    self.right.peek()?;
+                    (self.pad)()
+                }
+            },
+            false => self.right.next().unwrap_or_else(&mut self.pad),
+        };
+
+        self.next_left = !self.next_left;
+
+        Some(item)
+    }
+}
+
+pub struct InterleaveCycleIterator<I: Iterator, J: Iterator> {
+    left: Peekable<I>,
+    right: Peekable<J>,
+    left_start: I,
+    right_start: J,
+    left_done: bool,
+    right_done: bool,
+    next_left: bool,
+}
+
+impl<I, J> Debug for InterleaveCycleIterator<I, J>
+where
+    I: Iterator + Debug,
+    I::Item: Debug,
+    J: Iterator + Debug,
+    J::Item: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InterleaveCycleIterator")
+            .field("left", &self.left)
+            .field("right", &self.right)
+            .field("left_start", &self.left_start)
+            .field("right_start", &self.right_start)
+            .field("left_done", &self.left_done)
+            .field("right_done", &self.right_done)
+            .field("next_left", &self.next_left)
+            .finish()
+    }
+}
+
+impl<I, J> Clone for InterleaveCycleIterator<I, J>
+where
+    I: Iterator + Clone,
+    I::Item: Clone,
+    J: Iterator + Clone,
+    J::Item: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            left: self.left.clone(),
+            right: self.right.clone(),
+            left_start: self.left_start.clone(),
+            right_start: self.right_start.clone(),
+            left_done: self.left_done,
+            right_done: self.right_done,
+            next_left: self.next_left,
+        }
+    }
+}
+
+impl<L, R> Iterator for InterleaveCycleIterator<L, R>
+where
+    L: Iterator + Clone,
+    R: Iterator<Item = L::Item> + Clone,
+{
+    type Item = L::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Only stop at the end of a pair, once both sides have been through at least once
+        if self.next_left && self.left_done && self.right_done {
+            return None;
+        }
+        // The right is only empty here if it was empty to begin with
+        if self.next_left && self.right.peek().is_none() {
+            return None;
+        }
+
+        // Sides are restarted as soon as they run out, so it's known before the next pair
+        let item = match self.next_left {
+            true => {
+                let item = self.left.next()?;
+                if self.left.peek().is_none() {
+                    self.left_done = true;
+                    self.left = self.left_start.clone().peekable();
+                }
+                item
+            }
+            false => {
+                let item = self.right.next()?;
+                if self.right.peek().is_none() {
+                    self.right_done = true;
+                    self.right = self.right_start.clone().peekable();
+                }
+                item
+            }
+        };
+
+        self.next_left = !self.next_left;
+
+        Some(item)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ZipWithIterator<I, J, F> {
+    left: I,
+    right: J,
+    func: F,
+}
+
+impl<L, R, F, O> Iterator for ZipWithIterator<L, R, F>
+where
+    L: Iterator,
+    R: Iterator,
+    F: FnMut(L::Item, R::Item) -> O,
+{
+    type Item = O;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let left = self.left.next()?;
+        let right = self.right.next()?;
+
+        Some((self.func)(left, right))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (left_lower, left_upper) = self.left.size_hint();
+        let (right_lower, right_upper) = self.right.size_hint();
+        let upper = match (left_upper, right_upper) {
+            (Some(left), Some(right)) => Some(left.min(right)),
+            (upper, None) | (None, upper) => upper,
+        };
+
+        (left_lower.min(right_lower), upper)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InterleaveTaggedIterator<I, J> {
+    inner: InterleaveIterator<I, J>,
+}
+
+impl<L, R> Iterator for InterleaveTaggedIterator<L, R>
+where
+    L: Iterator,
+    R: Iterator<Item = L::Item>,
+{
+    type Item = (usize, L::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tag = if self.inner.next_left { 0 } else { 1 };
+
+        self.inner.next().map(|item| (tag, item))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<L, R> ExactSizeIterator for InterleaveTaggedIterator<L, R>
+where
+    L: ExactSizeIterator,
+    R: ExactSizeIterator<Item = L::Item>,
+{
 }
 
-trait IntoInterleaveIterator: IntoIterator {
+pub trait IntoInterleaveIterator: IntoIterator {
     /// Interleaves 2 iterators, starting with the left. Keeps going until one runs out.
+    ///
+    /// ```
+    /// use iterators_extended::interleave::IntoInterleaveIterator;
+    ///
+    /// let c = vec![1, 2, 3].interleave(vec![4, 5, 6]).collect::<Vec<_>>();
+    /// assert_eq!(c, [1, 4, 2, 5, 3, 6]);
+    /// ```
     fn interleave<R>(self, other: R) -> InterleaveIterator<Self::IntoIter, R::IntoIter>
         where
             Self: Sized,
@@ -37,13 +354,203 @@ trait IntoInterleaveIterator: IntoIterator {
             next_left: true,
         }
     }
+
+    /// Interleaves 2 iterators, starting with the left. Once one runs out, the rest of the other
+    /// is yielded in one go.
+    fn interleave_then_rest<R>(
+        self,
+        other: R,
+    ) -> InterleaveThenRestIterator<Self::IntoIter, R::IntoIter>
+    where
+        Self: Sized,
+        R: IntoIterator<Item = Self::Item>,
+    {
+        InterleaveThenRestIterator {
+            left: self.into_iter().fuse(),
+            right: other.into_iter().fuse(),
+            next_left: true,
+        }
+    }
+
+    /// Interleaves 2 iterators, starting with the left. Once one runs out, its slots are filled
+    /// with values from the pad function until the other runs out too, so the output always comes
+    /// in complete left/right pairs.
+    fn interleave_pad<R, F>(
+        self,
+        other: R,
+        pad: F,
+    ) -> InterleavePadIterator<Self::IntoIter, R::IntoIter, F>
+    where
+        Self: Sized,
+        R: IntoIterator<Item = Self::Item>,
+        F: FnMut() -> Self::Item,
+    {
+        InterleavePadIterator {
+            left: self.into_iter().fuse(),
+            right: other.into_iter().fuse().peekable(),
+            pad,
+            next_left: true,
+        }
+    }
+
+    /// Interleaves 2 iterators, starting with the left. Whichever runs out first is restarted from
+    /// the beginning, until the longer one has been through once. If either is empty, nothing is
+    /// yielded.
+    fn interleave_cycle<R>(self, other: R) -> InterleaveCycleIterator<Self::IntoIter, R::IntoIter>
+    where
+        Self: Sized,
+        Self::IntoIter: Clone,
+        R: IntoIterator<Item = Self::Item>,
+        R::IntoIter: Clone,
+    {
+        let (left, right) = (self.into_iter(), other.into_iter());
+        InterleaveCycleIterator {
+            left: left.clone().peekable(),
+            right: right.clone().peekable(),
+            left_start: left,
+            right_start: right,
+            left_done: false,
+            right_done: false,
+            next_left: true,
+        }
+    }
+
+    /// Combines the items of 2 iterators pairwise with the given function. Stops once either runs
+    /// out, like zip.
+    fn zip_with<R, F, O>(self, other: R, func: F) -> ZipWithIterator<Self::IntoIter, R::IntoIter, F>
+    where
+        Self: Sized,
+        R: IntoIterator,
+        F: FnMut(Self::Item, R::Item) -> O,
+    {
+        ZipWithIterator {
+            left: self.into_iter(),
+            right: other.into_iter(),
+            func,
+        }
+    }
+
+    /// Interleaves 2 iterators of different item types, starting with the left. Keeps going until
+    /// one runs out.
+    fn interleave_either<R>(
+        self,
+        other: R,
+    ) -> InterleaveEitherIterator<Self::IntoIter, R::IntoIter>
+    where
+        Self: Sized,
+        R: IntoIterator,
+    {
+        InterleaveEitherIterator {
+            left: self.into_iter(),
+            right: other.into_iter(),
+            next_left: true,
+        }
+    }
+
+    /// Like interleave, but each item is paired with the index of the iterator it came from, 0
+    /// for the left and 1 for the right.
+    fn interleave_tagged<R>(self, other: R) -> InterleaveTaggedIterator<Self::IntoIter, R::IntoIter>
+    where
+        Self: Sized,
+        R: IntoIterator<Item = Self::Item>,
+    {
+        InterleaveTaggedIterator {
+            inner: self.interleave(other),
+        }
+    }
 }
 
 impl<I: IntoIterator> IntoInterleaveIterator for I {}
 
+#[derive(Debug, Clone)]
+pub struct KMergeIterator<I: Iterator> {
+    iters: Vec<I>,
+    heads: BinaryHeap<Reverse<(I::Item, usize)>>,
+}
+
+impl<I> Iterator for KMergeIterator<I>
+where
+    I: Iterator,
+    I::Item: Ord,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((item, index)) = self.heads.pop()?;
+        if let Some(next) = self.iters[index].next() {
+            self.heads.push(Reverse((next, index)));
+        }
+
+        Some(item)
+    }
+}
+
+/// Merges several sorted iterators into one sorted iterator.
+/// Equal items are yielded in the order of the iterators they came from.
+pub fn kmerge<I>(iters: Vec<I>) -> KMergeIterator<I::IntoIter>
+where
+    I: IntoIterator,
+    I::Item: Ord,
+{
+    let mut iters = iters.into_iter().map(|i| i.into_iter()).collect::<Vec<_>>();
+    let heads = iters
+        .iter_mut()
+        .enumerate()
+        .filter_map(|(index, iter)| iter.next().map(|item| Reverse((item, index))))
+        .collect();
+
+    KMergeIterator { iters, heads }
+}
+
+#[derive(Debug, Clone)]
+pub struct InterleaveAllIterator<I> {
+    iters: Vec<Fuse<I>>,
+    index: usize,
+    longest: bool,
+    done: bool,
+}
+
+impl<I: Iterator> Iterator for InterleaveAllIterator<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.iters.is_empty() {
+            return None;
+        }
+
+        // In longest mode, exhausted iterators are skipped until they've all run out
+        let attempts = if self.longest { self.iters.len() } else { 1 };
+        for _ in 0..attempts {
+            let item = self.iters[self.index].next();
+            self.index = (self.index + 1) % self.iters.len();
+            if item.is_some() {
+                return item;
+            }
+        }
+
+        self.done = true;
+        None
+    }
+}
+
+/// Round-robins between several iterators. If longest is false it stops as soon as the next
+/// iterator in turn runs out, otherwise it carries on with the rest until they've all run out.
+pub fn interleave_all<I: IntoIterator>(
+    iters: Vec<I>,
+    longest: bool,
+) -> InterleaveAllIterator<I::IntoIter> {
+    InterleaveAllIterator {
+        iters: iters.into_iter().map(|i| i.into_iter().fuse()).collect(),
+        index: 0,
+        longest,
+        done: false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::interleave::IntoInterleaveIterator;
+    use crate::bucket::Either;
+    use crate::interleave::{interleave_all, kmerge, IntoInterleaveIterator};
 
     #[test]
     fn test1() {
@@ -89,4 +596,167 @@ mod tests {
         let c = a.interleave(b).collect::<Vec<_>>();
         assert_eq!(out, c);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_len() {
+        for (a, b) in [
+            (vec![1, 2, 3, 4, 5], vec![6, 7, 8, 9, 10]),
+            (vec![1, 2, 3], vec![6, 7, 8, 9, 10]),
+            (vec![1, 2, 3, 4, 5], vec![6, 7, 8]),
+        ] {
+            let mut iter = a.interleave(b);
+            let mut remaining = iter.len();
+            assert_eq!(remaining, iter.clone().count());
+            while iter.next().is_some() {
+                remaining -= 1;
+                assert_eq!(iter.len(), remaining);
+            }
+            assert_eq!(remaining, 0);
+        }
+
+        assert_eq!((0..).interleave(0..3).size_hint(), (7, Some(7)));
+        assert_eq!((0..).interleave(0..).size_hint(), (usize::MAX, None));
+    }
+
+    #[test]
+    fn test_then_rest() {
+        let c = [1, 2].interleave_then_rest([10, 20, 30, 40]).collect::<Vec<_>>();
+        assert_eq!(c, [1, 10, 2, 20, 30, 40]);
+
+        // 1:4, with the shorter side on either end
+        let c = [1].interleave_then_rest([10, 20, 30, 40]).collect::<Vec<_>>();
+        assert_eq!(c, [1, 10, 20, 30, 40]);
+        let c = [1, 2, 3, 4].interleave_then_rest([10]).collect::<Vec<_>>();
+        assert_eq!(c, [1, 10, 2, 3, 4]);
+
+        // Compared to interleave, which stops as soon as one side runs out
+        let c = [1, 2, 3, 4].interleave([10]).collect::<Vec<_>>();
+        assert_eq!(c, [1, 10, 2]);
+    }
+
+    #[test]
+    fn test_pad() {
+        let c = [1, 2].interleave_pad([10, 20, 30, 40], || 0).collect::<Vec<_>>();
+        assert_eq!(c, [1, 10, 2, 20, 0, 30, 0, 40]);
+
+        let c = [1, 2, 3].interleave_pad([10], || 0).collect::<Vec<_>>();
+        assert_eq!(c, [1, 10, 2, 0, 3, 0]);
+
+        let c = [1, 2].interleave_pad([10, 20], || 0).collect::<Vec<_>>();
+        assert_eq!(c, [1, 10, 2, 20]);
+
+        // Clones carry on from the same place, including the peeked item on the right
+        let mut c = [1].interleave_pad([10, 20], i32::default as fn() -> i32);
+        c.next();
+        assert_eq!(c.clone().collect::<Vec<_>>(), [10, 0, 20]);
+        assert!(format!("{:?}", c).starts_with("InterleavePadIterator"));
+    }
+
+    #[test]
+    fn test_cycle() {
+        let c = ["a", "b"]
+            .interleave_cycle(["1", "2", "3", "4"])
+            .collect::<Vec<_>>();
+        assert_eq!(c, ["a", "1", "b", "2", "a", "3", "b", "4"]);
+
+        let c = [1, 2, 3, 4].interleave_cycle([10, 20]).collect::<Vec<_>>();
+        assert_eq!(c, [1, 10, 2, 20, 3, 10, 4, 20]);
+
+        let c = [1, 2].interleave_cycle([10, 20]).collect::<Vec<_>>();
+        assert_eq!(c, [1, 10, 2, 20]);
+
+        assert_eq!([1, 2].interleave_cycle([]).count(), 0);
+        assert_eq!([].interleave_cycle([1, 2]).count(), 0);
+
+        let mut c = [1, 2].interleave_cycle([10, 20, 30]);
+        c.next();
+        assert_eq!(c.clone().collect::<Vec<_>>(), [10, 2, 20, 1, 30]);
+        assert!(format!("{:?}", c).starts_with("InterleaveCycleIterator"));
+    }
+
+    #[test]
+    fn test_zip_with() {
+        let c = [1, 2, 3].zip_with([10, 20, 30], |a, b| a + b).collect::<Vec<_>>();
+        assert_eq!(c, [11, 22, 33]);
+
+        let c = [1, 2, 3].zip_with(["a"], |a, b| format!("{b}{a}"));
+        assert_eq!(c.collect::<Vec<_>>(), ["a1"]);
+        assert_eq!((0..).zip_with(0..4, |a, b| a * b).size_hint(), (4, Some(4)));
+    }
+
+    #[test]
+    fn test_either() {
+        let c = [1, 2].interleave_either(["a", "b"]).collect::<Vec<_>>();
+        assert_eq!(
+            c,
+            [
+                Either::Left(1),
+                Either::Right("a"),
+                Either::Left(2),
+                Either::Right("b")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tagged() {
+        let c = [1, 2].interleave_tagged([3, 4]).collect::<Vec<_>>();
+        assert_eq!(c, [(0, 1), (1, 3), (0, 2), (1, 4)]);
+
+        let c = [1, 2, 5].interleave_tagged([3]);
+        assert_eq!(c.len(), 3);
+        assert_eq!(c.collect::<Vec<_>>(), [(0, 1), (1, 3), (0, 2)]);
+    }
+
+    #[test]
+    fn test_interleave_all() {
+        let c = interleave_all(vec![0..3, 10..15, 20..22], false).collect::<Vec<_>>();
+        assert_eq!(c, [0, 10, 20, 1, 11, 21, 2, 12]);
+
+        let c = interleave_all(vec![0..3, 10..15, 20..22], true).collect::<Vec<_>>();
+        assert_eq!(c, [0, 10, 20, 1, 11, 21, 2, 12, 13, 14]);
+
+        assert_eq!(interleave_all(Vec::<Vec<i32>>::new(), true).count(), 0);
+    }
+
+    #[test]
+    fn test_kmerge() {
+        let merged = kmerge(vec![0..10, 5..8, 3..4]).collect::<Vec<_>>();
+        let mut out = (0..10).chain(5..8).chain(3..4).collect::<Vec<_>>();
+        out.sort();
+        assert_eq!(merged, out);
+
+        // Empty inputs
+        assert_eq!(kmerge(Vec::<Vec<i32>>::new()).count(), 0);
+        let merged = kmerge(vec![vec![], vec![1, 3], vec![]]).collect::<Vec<_>>();
+        assert_eq!(merged, [1, 3]);
+
+        // Duplicates come out in source order
+        #[derive(Debug, PartialEq, Eq)]
+        struct Tagged(i32, char);
+        impl PartialOrd for Tagged {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Tagged {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+
+        let merged = kmerge(vec![
+            vec![Tagged(1, 'a'), Tagged(2, 'a')],
+            vec![Tagged(1, 'b'), Tagged(2, 'b')],
+        ])
+        .map(|t| t.1)
+        .collect::<Vec<_>>();
+        assert_eq!(merged, ['a', 'b', 'a', 'b']);
+
+        // Clones carry on from the same place, including the buffered heads
+        let mut merged = kmerge(vec![vec![1, 4], vec![2, 3]]);
+        merged.next();
+        assert_eq!(merged.clone().collect::<Vec<_>>(), [2, 3, 4]);
+        assert_eq!(merged.collect::<Vec<_>>(), [2, 3, 4]);
+    }
+}
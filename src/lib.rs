@@ -1,7 +1,17 @@
+pub mod batch;
+pub mod bucket;
 pub mod buffered;
+pub mod dedup;
+pub mod flatten;
 pub mod interleave;
 pub mod logging;
+pub mod misc;
+pub mod prelude;
+pub mod sample;
+pub mod split;
 pub mod stateful;
 pub mod stateful_threaded;
+pub mod tee;
 pub mod threaded;
-pub mod groupby;
+pub mod throttle;
+pub mod windows;
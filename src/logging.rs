@@ -1,10 +1,13 @@
 use std::fmt::Debug;
+use std::io::{self, Stderr, Write};
 
-pub struct LoggingIterator<I: Iterator> {
+#[derive(Debug, Clone)]
+pub struct LoggingIterator<I: Iterator, W = Stderr> {
     inner: I,
+    writer: W,
 }
 
-impl<T, E: Debug, I: Iterator<Item=Result<T, E>>> Iterator for LoggingIterator<I>
+impl<T, E: Debug, I: Iterator<Item=Result<T, E>>, W: Write> Iterator for LoggingIterator<I, W>
 {
     type Item = T;
 
@@ -12,21 +15,88 @@ impl<T, E: Debug, I: Iterator<Item=Result<T, E>>> Iterator for LoggingIterator<I
         self.inner.find_map(|result| match result {
             Ok(val) => Some(val),
             Err(e) => {
-                eprintln!("{:?}", e);
+                // Failing to log shouldn't interrupt the stream, so write errors are ignored
+                let _ = writeln!(self.writer, "{:?}", e);
                 None
             }
         })
     }
 }
 
-trait IntoLoggingIterator {
+#[derive(Debug, Clone)]
+pub struct MapLoggingIterator<I: Iterator, G, W = Stderr> {
+    inner: I,
+    map_err: G,
+    writer: W,
+}
+
+impl<T, E, E2: Debug, I: Iterator<Item=Result<T, E>>, G: Fn(E) -> E2, W: Write> Iterator for MapLoggingIterator<I, G, W>
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.find_map(|result| match result {
+            Ok(val) => Some(val),
+            Err(e) => {
+                let _ = writeln!(self.writer, "{:?}", (self.map_err)(e));
+                None
+            }
+        })
+    }
+}
+
+pub trait IntoLoggingIterator {
     /// Filters out errors, printing them to stderr. Ok results are unwrapped.
+    ///
+    /// ```
+    /// use iterators_extended::logging::IntoLoggingIterator;
+    ///
+    /// let numbers = ["1", "two", "3"]
+    ///     .into_iter()
+    ///     .map(|x| x.parse::<i32>())
+    ///     .filter_log()
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(numbers, [1, 3]);
+    /// ```
     fn filter_log<T, E: Debug>(self) -> LoggingIterator<Self>
         where
             Self: Sized + Iterator<Item=Result<T, E>>
     {
         LoggingIterator {
-            inner: self
+            inner: self,
+            writer: io::stderr(),
+        }
+    }
+
+    /// Filters out errors, writing them to the given writer. Ok results are unwrapped.
+    fn filter_log_to<T, E: Debug, W: Write>(self, writer: W) -> LoggingIterator<Self, W>
+        where
+            Self: Sized + Iterator<Item=Result<T, E>>
+    {
+        LoggingIterator {
+            inner: self,
+            writer,
+        }
+    }
+
+    /// Like filter_log, but errors are passed through the given function before being printed,
+    /// eg. to add context or redact them.
+    fn filter_log_map<T, E, E2: Debug, G: Fn(E) -> E2>(self, map_err: G) -> MapLoggingIterator<Self, G>
+        where
+            Self: Sized + Iterator<Item=Result<T, E>>
+    {
+        self.filter_log_map_to(io::stderr(), map_err)
+    }
+
+    /// Like filter_log_map, but the mapped errors are written to the given writer.
+    fn filter_log_map_to<T, E, E2: Debug, G: Fn(E) -> E2, W: Write>(self, writer: W, map_err: G) -> MapLoggingIterator<Self, G, W>
+        where
+            Self: Sized + Iterator<Item=Result<T, E>>
+    {
+        MapLoggingIterator {
+            inner: self,
+            map_err,
+            writer,
         }
     }
 }
@@ -46,4 +116,31 @@ mod tests {
             .collect::<Vec<_>>();
         assert_eq!(y, ["a", "c"]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_to_writer() {
+        let x = [Ok("a"), Err("b"), Ok("c"), Err("d")];
+        let mut log = vec![];
+        let y = x.into_iter()
+            .filter_log_to(&mut log)
+            .collect::<Vec<_>>();
+        assert_eq!(y, ["a", "c"]);
+        assert_eq!(String::from_utf8(log).unwrap(), "\"b\"\n\"d\"\n");
+    }
+
+    #[test]
+    fn test_map() {
+        let x = [Ok(1), Err("bad"), Ok(2)];
+        let y = x.into_iter()
+            .filter_log_map(|e| format!("while parsing: {e}"))
+            .collect::<Vec<_>>();
+        assert_eq!(y, [1, 2]);
+
+        let mut log = vec![];
+        let y = x.into_iter()
+            .filter_log_map_to(&mut log, |e| format!("while parsing: {e}"))
+            .collect::<Vec<_>>();
+        assert_eq!(y, [1, 2]);
+        assert_eq!(String::from_utf8(log).unwrap(), "\"while parsing: bad\"\n");
+    }
+}
@@ -0,0 +1,296 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::iter::Zip;
+use std::ops::RangeFrom;
+
+pub trait IntoEnumerateFromIterator: IntoIterator {
+    /// Like enumerate, but the indices start from the given value rather than 0.
+    fn enumerate_from(self, start: usize) -> Zip<RangeFrom<usize>, Self::IntoIter>
+    where
+        Self: Sized,
+    {
+        (start..).zip(self)
+    }
+}
+
+impl<I: IntoIterator> IntoEnumerateFromIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct TakeUntilIterator<I, F> {
+    iter: I,
+    pred: F,
+    done: bool,
+}
+
+impl<I, F> Iterator for TakeUntilIterator<I, F>
+where
+    I: Iterator,
+    F: FnMut(&I::Item) -> bool,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let item = self.iter.next()?;
+        self.done = (self.pred)(&item);
+
+        Some(item)
+    }
+}
+
+pub trait IntoTakeUntilIterator: IntoIterator {
+    /// Like take_while, but with the predicate flipped, and the first item that matches it is
+    /// yielded too before stopping. If no item matches, everything is yielded.
+    fn take_until<F>(self, pred: F) -> TakeUntilIterator<Self::IntoIter, F>
+    where
+        Self: Sized,
+        F: FnMut(&Self::Item) -> bool,
+    {
+        TakeUntilIterator {
+            iter: self.into_iter(),
+            pred,
+            done: false,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoTakeUntilIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct RepeatEachIterator<I: Iterator> {
+    iter: I,
+    n: usize,
+    current: Option<I::Item>,
+    remaining: usize,
+}
+
+impl<I> Iterator for RepeatEachIterator<I>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.n == 0 {
+            return None;
+        }
+
+        if self.remaining == 0 {
+            self.current = Some(self.iter.next()?);
+            self.remaining = self.n;
+        }
+
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            // Last repeat, so the item can be moved out rather than cloned
+            self.current.take()
+        } else {
+            self.current.clone()
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.n == 0 {
+            return (0, Some(0));
+        }
+
+        let (lower, upper) = self.iter.size_hint();
+        let repeat = |x: usize| x.checked_mul(self.n)?.checked_add(self.remaining);
+
+        (repeat(lower).unwrap_or(usize::MAX), upper.and_then(repeat))
+    }
+}
+
+pub trait IntoRepeatEachIterator: IntoIterator {
+    /// Yield each item n times in a row before moving on to the next one. If n is 0, nothing is
+    /// yielded.
+    fn repeat_each(self, n: usize) -> RepeatEachIterator<Self::IntoIter>
+    where
+        Self: Sized,
+        Self::Item: Clone,
+    {
+        RepeatEachIterator {
+            iter: self.into_iter(),
+            n,
+            current: None,
+            remaining: 0,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoRepeatEachIterator for I {}
+
+/// Consumers which reduce the iterator to a single value.
+pub trait Consume {
+    /// Collect a stream of results into a single result, holding either all of the values or the
+    /// first error. Iteration stops as soon as an error is seen.
+    fn collect_results<V, E>(self) -> Result<Vec<V>, E>
+    where
+        Self: Iterator<Item = Result<V, E>> + Sized,
+    {
+        let mut values = vec![];
+        for item in self {
+            values.push(item?);
+        }
+
+        Ok(values)
+    }
+
+    /// Find the k largest items, in descending order. Only k items are held in memory at a time.
+    fn top_k(self, k: usize) -> Vec<Self::Item>
+    where
+        Self: Iterator + Sized,
+        Self::Item: Ord,
+    {
+        if k == 0 {
+            return vec![];
+        }
+
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        for item in self {
+            heap.push(Reverse(item));
+            if heap.len() > k {
+                heap.pop();
+            }
+        }
+
+        // Ascending order of Reverse is descending order of the items
+        heap.into_sorted_vec().into_iter().map(|x| x.0).collect()
+    }
+
+    /// Find the index of the largest item. If there are several, the first one wins.
+    fn position_max(self) -> Option<usize>
+    where
+        Self: Iterator + Sized,
+        Self::Item: Ord,
+    {
+        let mut best: Option<(usize, Self::Item)> = None;
+        for (i, item) in self.enumerate() {
+            if best.as_ref().is_none_or(|(_, max)| item > *max) {
+                best = Some((i, item));
+            }
+        }
+
+        best.map(|(i, _)| i)
+    }
+
+    /// Find the index of the smallest item. If there are several, the first one wins.
+    fn position_min(self) -> Option<usize>
+    where
+        Self: Iterator + Sized,
+        Self::Item: Ord,
+    {
+        let mut best: Option<(usize, Self::Item)> = None;
+        for (i, item) in self.enumerate() {
+            if best.as_ref().is_none_or(|(_, min)| item < *min) {
+                best = Some((i, item));
+            }
+        }
+
+        best.map(|(i, _)| i)
+    }
+
+    /// Keep the last n items, in their original order. Only n items are held in memory at a time.
+    /// If there are fewer than n items, all of them are returned.
+    fn last_n(self, n: usize) -> Vec<Self::Item>
+    where
+        Self: Iterator + Sized,
+    {
+        if n == 0 {
+            return vec![];
+        }
+
+        let mut last = VecDeque::with_capacity(n);
+        for item in self {
+            if last.len() == n {
+                last.pop_front();
+            }
+            last.push_back(item);
+        }
+
+        last.into()
+    }
+}
+
+impl<T: Iterator + Sized> Consume for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Consume, IntoEnumerateFromIterator, IntoRepeatEachIterator, IntoTakeUntilIterator,
+    };
+    use std::cell::Cell;
+
+    #[test]
+    fn test_enumerate_from() {
+        let items = ["a", "b"].enumerate_from(10).collect::<Vec<_>>();
+        assert_eq!(items, [(10, "a"), (11, "b")]);
+    }
+
+    #[test]
+    fn test_take_until() {
+        let items = [1, 2, 3, 4].take_until(|x| *x >= 3).collect::<Vec<_>>();
+        assert_eq!(items, [1, 2, 3]);
+
+        let items = [1, 2, 3, 4].take_until(|x| *x > 10).collect::<Vec<_>>();
+        assert_eq!(items, [1, 2, 3, 4]);
+
+        let line = "ab\ncd"
+            .chars()
+            .take_until(|c| *c == '\n')
+            .collect::<String>();
+        assert_eq!(line, "ab\n");
+    }
+
+    #[test]
+    fn test_repeat_each() {
+        let items = ["a", "b"].repeat_each(2).collect::<Vec<_>>();
+        assert_eq!(items, ["a", "a", "b", "b"]);
+
+        assert_eq!([1, 2, 3].repeat_each(1).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!([1, 2, 3].repeat_each(0).count(), 0);
+        assert_eq!((0..4).repeat_each(3).size_hint(), (12, Some(12)));
+    }
+
+    #[test]
+    fn test_collect_results() {
+        let items: [Result<i32, &str>; 3] = [Ok(1), Ok(2), Ok(3)];
+        assert_eq!(items.into_iter().collect_results(), Ok(vec![1, 2, 3]));
+
+        let seen = Cell::new(0);
+        let items: [Result<i32, &str>; 5] = [Ok(1), Ok(2), Err("bad"), Ok(4), Err("worse")];
+        let result = items
+            .into_iter()
+            .inspect(|_| seen.set(seen.get() + 1))
+            .collect_results();
+        assert_eq!(result, Err("bad"));
+        assert_eq!(seen.get(), 3);
+    }
+
+    #[test]
+    fn test_top_k() {
+        assert_eq!((0..100).top_k(3), [99, 98, 97]);
+        assert_eq!([5, 1, 9, 3, 9].into_iter().top_k(3), [9, 9, 5]);
+        assert_eq!([2, 3, 1].into_iter().top_k(5), [3, 2, 1]);
+        assert_eq!((0..100).top_k(0), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn test_position_max_min() {
+        assert_eq!([3, 7, 2, 7].into_iter().position_max(), Some(1));
+        assert_eq!([3, 7, 2, 2].into_iter().position_min(), Some(2));
+        assert_eq!(std::iter::empty::<i32>().position_max(), None);
+        assert_eq!(std::iter::empty::<i32>().position_min(), None);
+    }
+
+    #[test]
+    fn test_last_n() {
+        assert_eq!((0..10).last_n(3), [7, 8, 9]);
+        assert_eq!((0..2).last_n(3), [0, 1]);
+        assert_eq!((0..10).last_n(0), Vec::<i32>::new());
+    }
+}
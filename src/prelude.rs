@@ -0,0 +1,44 @@
+//! Re-exports all of the iterator extension traits, so every adapter is available from one import.
+//!
+//! ```
+//! use iterators_extended::prelude::*;
+//!
+//! let buckets = (0..10)
+//!     .buffered(4)
+//!     .par_map(|x| x * x)
+//!     .bucket(2, |x| x % 2);
+//! assert_eq!(buckets, [vec![0, 4, 16, 36, 64], vec![1, 9, 25, 49, 81]]);
+//! ```
+
+pub use crate::batch::{
+    IntoBatchBySizeIterator, IntoChunkByKeyCappedIterator, IntoChunkByTimeIterator,
+    IntoFramesIterator, IntoGroupRunsIterator,
+};
+pub use crate::bucket::Bucket;
+pub use crate::buffered::IntoBufferedIterator;
+pub use crate::dedup::{
+    IntoCoalesceIterator, IntoDedupWindowIterator, IntoDedupWithCountIterator,
+    IntoTakeUntilRepeatIterator,
+};
+pub use crate::flatten::IntoFlattenOptionsIterator;
+pub use crate::interleave::IntoInterleaveIterator;
+pub use crate::logging::IntoLoggingIterator;
+pub use crate::misc::{
+    Consume, IntoEnumerateFromIterator, IntoRepeatEachIterator, IntoTakeUntilIterator,
+};
+pub use crate::sample::Sample;
+pub use crate::split::SplitAtPredicate;
+pub use crate::stateful::{
+    IntoCumulativeSumIterator, IntoRunningFoldIterator, IntoRunningMinMaxIterator,
+    IntoSharedStatefulMapIterator, IntoSlidingSumIterator, IntoStatefulFinishMapIterator,
+    IntoStatefulIndexedMapIterator, IntoStatefulInspectMapIterator, IntoStatefulMapIterator,
+    IntoStatefulOkMapIterator, IntoStatefulPeekMapIterator, IntoStatefulResetMapIterator,
+    IntoStatefulTryMapIterator,
+};
+pub use crate::stateful_threaded::IntoStatefulThreadedIterator;
+pub use crate::tee::IntoTeeIterator;
+pub use crate::threaded::{IntoThreadedIterator, MaybeParMap};
+pub use crate::throttle::{IntoMeteredIterator, IntoThrottleIterator};
+pub use crate::windows::{
+    IntoContextWindowsIterator, IntoTupleWindowsIterator, IntoWithPreviousIterator,
+};
@@ -0,0 +1,88 @@
+use std::iter::StepBy;
+
+/// A small seeded random number generator (SplitMix64), so sampling is reproducible without
+/// pulling in a dependency.
+#[derive(Debug, Clone)]
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in 0..bound
+    fn below(&mut self, bound: usize) -> usize {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as usize
+    }
+}
+
+pub trait Sample: IntoIterator {
+    /// Yields the first item, then every nth item after it.
+    /// Panics if n is 0.
+    fn sample_every(self, n: usize) -> StepBy<Self::IntoIter>
+    where
+        Self: Sized,
+    {
+        self.into_iter().step_by(n)
+    }
+
+    /// Consumes the iterator, returning a uniformly random sample of k items using reservoir
+    /// sampling. Only k items are held in memory at once. The same seed gives the same sample.
+    /// If there are fewer than k items, all of them are returned.
+    fn reservoir_sample(self, k: usize, seed: u64) -> Vec<Self::Item>
+    where
+        Self: Sized,
+    {
+        let mut rng = SplitMix64::new(seed);
+        let mut reservoir = Vec::with_capacity(k);
+
+        for (i, item) in self.into_iter().enumerate() {
+            if i < k {
+                reservoir.push(item);
+            } else {
+                let j = rng.below(i + 1);
+                if j < k {
+                    reservoir[j] = item;
+                }
+            }
+        }
+
+        reservoir
+    }
+}
+
+impl<I: IntoIterator> Sample for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::Sample;
+
+    #[test]
+    fn test_sample_every() {
+        let sampled = (0..9).sample_every(3).collect::<Vec<_>>();
+        assert_eq!(sampled, [0, 3, 6]);
+    }
+
+    #[test]
+    fn test_reservoir_sample() {
+        let sample = (0..1000).reservoir_sample(10, 42);
+        assert_eq!(sample.len(), 10);
+        assert!(sample.iter().all(|x| (0..1000).contains(x)));
+
+        // Reproducible with the same seed
+        assert_eq!(sample, (0..1000).reservoir_sample(10, 42));
+
+        // Fewer items than the sample size
+        assert_eq!((0..5).reservoir_sample(10, 42), [0, 1, 2, 3, 4]);
+    }
+}
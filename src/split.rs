@@ -0,0 +1,52 @@
+use std::iter::Chain;
+use std::option;
+
+/// The rest of the items, starting from the one that matched.
+pub type SplitTail<I> = Chain<option::IntoIter<<I as Iterator>::Item>, I>;
+
+pub trait SplitAtPredicate: IntoIterator {
+    /// Splits the items at the first one matching the predicate.
+    /// The head is collected into memory, and the tail continues lazily from the matching item.
+    /// If nothing matches, the tail is empty.
+    fn split_at_predicate<F>(self, pred: F) -> (Vec<Self::Item>, SplitTail<Self::IntoIter>)
+    where
+        Self: Sized,
+        F: Fn(&Self::Item) -> bool,
+    {
+        let mut iter = self.into_iter();
+        let mut head = vec![];
+        let mut first = None;
+
+        for item in iter.by_ref() {
+            if pred(&item) {
+                first = Some(item);
+                break;
+            }
+            head.push(item);
+        }
+
+        (head, first.into_iter().chain(iter))
+    }
+}
+
+impl<I: IntoIterator> SplitAtPredicate for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::SplitAtPredicate;
+
+    #[test]
+    fn test_split() {
+        let values = vec![3, 1, 4, -1, 5, -9, 2];
+        let (head, tail) = values.split_at_predicate(|x| *x < 0);
+        assert_eq!(head, [3, 1, 4]);
+        assert_eq!(tail.collect::<Vec<_>>(), [-1, 5, -9, 2]);
+    }
+
+    #[test]
+    fn test_split_no_match() {
+        let (head, tail) = (0..5).split_at_predicate(|x| *x < 0);
+        assert_eq!(head, [0, 1, 2, 3, 4]);
+        assert_eq!(tail.count(), 0);
+    }
+}
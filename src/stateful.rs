@@ -1,3 +1,10 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Debug, Formatter};
+use std::iter::{Flatten, Peekable};
+use std::ops::{Add, Sub};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone)]
 pub struct StatefulMapIterator<I, S, F> {
     state: S,
     iter: I,
@@ -30,9 +37,597 @@ pub trait IntoStatefulMapIterator: IntoIterator {
 
 impl<I: IntoIterator> IntoStatefulMapIterator for I {}
 
+pub struct StatefulPeekMapIterator<I: Iterator, S, F> {
+    state: S,
+    iter: Peekable<I>,
+    func: F,
+}
+
+impl<I, S, F> Clone for StatefulPeekMapIterator<I, S, F>
+where
+    I: Iterator + Clone,
+    I::Item: Clone,
+    S: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            iter: self.iter.clone(),
+            func: self.func.clone(),
+        }
+    }
+}
+
+impl<I, S, F> Debug for StatefulPeekMapIterator<I, S, F>
+where
+    I: Iterator + Debug,
+    I::Item: Debug,
+    S: Debug,
+    F: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StatefulPeekMapIterator")
+            .field("state", &self.state)
+            .field("iter", &self.iter)
+            .field("func", &self.func)
+            .finish()
+    }
+}
+
+impl<I: Iterator, S, F, FO> Iterator for StatefulPeekMapIterator<I, S, F>
+where
+    F: FnMut(&mut S, I::Item, Option<&I::Item>) -> FO,
+{
+    type Item = FO;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        Some((self.func)(&mut self.state, item, self.iter.peek()))
+    }
+}
+
+pub trait IntoStatefulPeekMapIterator: IntoIterator {
+    /// Like stateful_map, but the function also gets a peek at the next item (None on the last).
+    fn stateful_map_peek<S, F, FO>(
+        self,
+        state: S,
+        func: F,
+    ) -> StatefulPeekMapIterator<Self::IntoIter, S, F>
+    where
+        Self: Sized,
+        F: FnMut(&mut S, Self::Item, Option<&Self::Item>) -> FO,
+    {
+        StatefulPeekMapIterator {
+            iter: self.into_iter().peekable(),
+            state,
+            func,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoStatefulPeekMapIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct StatefulTryMapIterator<I, S, F> {
+    state: S,
+    iter: I,
+    func: F,
+    failed: bool,
+}
+
+impl<I: Iterator, S, F, FO, E> Iterator for StatefulTryMapIterator<I, S, F>
+where
+    F: FnMut(&mut S, I::Item) -> Result<FO, E>,
+{
+    type Item = Result<FO, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let result = (self.func)(&mut self.state, self.iter.next()?);
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
+
+pub trait IntoStatefulTryMapIterator: IntoIterator {
+    /// Like stateful_map, but for functions that can fail.
+    /// The first error is yielded and then the iterator stops, since the state may no longer be valid.
+    fn stateful_try_map<S, F, FO, E>(
+        self,
+        state: S,
+        func: F,
+    ) -> StatefulTryMapIterator<Self::IntoIter, S, F>
+    where
+        Self: Sized,
+        F: FnMut(&mut S, Self::Item) -> Result<FO, E>,
+    {
+        StatefulTryMapIterator {
+            iter: self.into_iter(),
+            state,
+            func,
+            failed: false,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoStatefulTryMapIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct StatefulOkMapIterator<I, S, F> {
+    state: S,
+    iter: I,
+    func: F,
+}
+
+impl<I, S, F, T, E, U> Iterator for StatefulOkMapIterator<I, S, F>
+where
+    I: Iterator<Item = Result<T, E>>,
+    F: FnMut(&mut S, T) -> U,
+{
+    type Item = Result<U, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        Some(item.map(|x| (self.func)(&mut self.state, x)))
+    }
+}
+
+pub trait IntoStatefulOkMapIterator: IntoIterator {
+    /// Like stateful_map, but over a stream of results. Only the Ok values are mapped and update
+    /// the state, errors are passed through untouched.
+    fn stateful_map_ok<S, F, T, E, U>(
+        self,
+        state: S,
+        func: F,
+    ) -> StatefulOkMapIterator<Self::IntoIter, S, F>
+    where
+        Self: Sized + IntoIterator<Item = Result<T, E>>,
+        F: FnMut(&mut S, T) -> U,
+    {
+        StatefulOkMapIterator {
+            iter: self.into_iter(),
+            state,
+            func,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoStatefulOkMapIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct StatefulResetMapIterator<I, S, M, F, B> {
+    state: S,
+    make_state: M,
+    iter: I,
+    func: F,
+    is_boundary: B,
+}
+
+impl<I: Iterator, S, M, F, B, FO> Iterator for StatefulResetMapIterator<I, S, M, F, B>
+where
+    M: Fn() -> S,
+    F: FnMut(&mut S, I::Item) -> FO,
+    B: Fn(&I::Item) -> bool,
+{
+    type Item = FO;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        if (self.is_boundary)(&item) {
+            self.state = (self.make_state)();
+        }
+
+        Some((self.func)(&mut self.state, item))
+    }
+}
+
+pub trait IntoStatefulResetMapIterator: IntoIterator {
+    /// Like stateful_map, but the state is re-created before processing any item that is a boundary.
+    fn stateful_map_reset<S, M, F, B, FO>(
+        self,
+        make_state: M,
+        func: F,
+        is_boundary: B,
+    ) -> StatefulResetMapIterator<Self::IntoIter, S, M, F, B>
+    where
+        Self: Sized,
+        M: Fn() -> S,
+        F: FnMut(&mut S, Self::Item) -> FO,
+        B: Fn(&Self::Item) -> bool,
+    {
+        StatefulResetMapIterator {
+            state: make_state(),
+            make_state,
+            iter: self.into_iter(),
+            func,
+            is_boundary,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoStatefulResetMapIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct StatefulIndexedMapIterator<I, S, F> {
+    state: S,
+    iter: I,
+    func: F,
+    index: usize,
+}
+
+impl<I: Iterator, S, F, FO> Iterator for StatefulIndexedMapIterator<I, S, F>
+where
+    F: FnMut(&mut S, usize, I::Item) -> FO,
+{
+    type Item = FO;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        let out = (self.func)(&mut self.state, self.index, item);
+        self.index += 1;
+        Some(out)
+    }
+}
+
+pub trait IntoStatefulIndexedMapIterator: IntoIterator {
+    /// Like stateful_map, but the function also gets the zero-based index of each item.
+    fn stateful_map_indexed<S, F, FO>(
+        self,
+        state: S,
+        func: F,
+    ) -> StatefulIndexedMapIterator<Self::IntoIter, S, F>
+    where
+        Self: Sized,
+        F: FnMut(&mut S, usize, Self::Item) -> FO,
+    {
+        StatefulIndexedMapIterator {
+            iter: self.into_iter(),
+            state,
+            func,
+            index: 0,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoStatefulIndexedMapIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct StatefulInspectMapIterator<I, S, F, G> {
+    state: S,
+    iter: I,
+    func: F,
+    inspect: G,
+}
+
+impl<I: Iterator, S, F, G, FO> Iterator for StatefulInspectMapIterator<I, S, F, G>
+where
+    F: FnMut(&mut S, I::Item) -> FO,
+    G: FnMut(&S),
+{
+    type Item = FO;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        let out = (self.func)(&mut self.state, item);
+        (self.inspect)(&self.state);
+        Some(out)
+    }
+}
+
+pub trait IntoStatefulInspectMapIterator: IntoIterator {
+    /// Like stateful_map, but the inspect function is shown the state after each update, eg. for
+    /// debugging. It doesn't change the outputs.
+    fn stateful_map_inspect<S, F, G, FO>(
+        self,
+        state: S,
+        func: F,
+        inspect: G,
+    ) -> StatefulInspectMapIterator<Self::IntoIter, S, F, G>
+    where
+        Self: Sized,
+        F: FnMut(&mut S, Self::Item) -> FO,
+        G: FnMut(&S),
+    {
+        StatefulInspectMapIterator {
+            iter: self.into_iter(),
+            state,
+            func,
+            inspect,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoStatefulInspectMapIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct StatefulFinishMapIterator<I, S, F, G> {
+    iter: I,
+    func: F,
+    // Taken once the source runs out, so the finish function is only called once
+    rest: Option<(S, G)>,
+}
+
+impl<I: Iterator, S, F, G, FO> Iterator for StatefulFinishMapIterator<I, S, F, G>
+where
+    F: FnMut(&mut S, I::Item) -> FO,
+    G: FnOnce(S) -> Option<FO>,
+{
+    type Item = FO;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (state, _) = self.rest.as_mut()?;
+        match self.iter.next() {
+            Some(item) => Some((self.func)(state, item)),
+            None => {
+                let (state, finish) = self.rest.take()?;
+                finish(state)
+            }
+        }
+    }
+}
+
+pub trait IntoStatefulFinishMapIterator: IntoIterator {
+    /// Like stateful_map, but once the items run out the finish function is given the final state,
+    /// and can yield one last item, eg. to flush a partially filled buffer.
+    fn stateful_map_with_finish<S, F, G, FO>(
+        self,
+        state: S,
+        func: F,
+        finish: G,
+    ) -> StatefulFinishMapIterator<Self::IntoIter, S, F, G>
+    where
+        Self: Sized,
+        F: FnMut(&mut S, Self::Item) -> FO,
+        G: FnOnce(S) -> Option<FO>,
+    {
+        StatefulFinishMapIterator {
+            iter: self.into_iter(),
+            func,
+            rest: Some((state, finish)),
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoStatefulFinishMapIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct RunningFoldIterator<I, A, F> {
+    acc: A,
+    iter: I,
+    func: F,
+    started: bool,
+}
+
+impl<I: Iterator, A: Clone, F> Iterator for RunningFoldIterator<I, A, F>
+where
+    F: FnMut(&A, I::Item) -> A,
+{
+    type Item = A;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started {
+            self.started = true;
+            return Some(self.acc.clone());
+        }
+
+        let item = self.iter.next()?;
+        self.acc = (self.func)(&self.acc, item);
+        Some(self.acc.clone())
+    }
+}
+
+pub trait IntoRunningFoldIterator: IntoIterator {
+    /// Folds the items, yielding every intermediate accumulator, starting with the initial value.
+    fn running_fold<A, F>(self, init: A, func: F) -> RunningFoldIterator<Self::IntoIter, A, F>
+    where
+        Self: Sized,
+        A: Clone,
+        F: FnMut(&A, Self::Item) -> A,
+    {
+        RunningFoldIterator {
+            acc: init,
+            iter: self.into_iter(),
+            func,
+            started: false,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoRunningFoldIterator for I {}
+
+type MinMaxFn<T> = fn(&mut Option<(T, T)>, T) -> (T, T, T);
+
+pub type RunningMinMaxIterator<I> = StatefulMapIterator<
+    I,
+    Option<(<I as Iterator>::Item, <I as Iterator>::Item)>,
+    MinMaxFn<<I as Iterator>::Item>,
+>;
+
+fn update_min_max<T: Ord + Clone>(state: &mut Option<(T, T)>, x: T) -> (T, T, T) {
+    let (min, max) = match state.take() {
+        Some((min, max)) => (min.min(x.clone()), max.max(x.clone())),
+        None => (x.clone(), x.clone()),
+    };
+    *state = Some((min.clone(), max.clone()));
+
+    (x, min, max)
+}
+
+pub trait IntoRunningMinMaxIterator: IntoIterator {
+    /// Yields each item along with the minimum and maximum seen so far (including itself).
+    fn running_minmax(self) -> RunningMinMaxIterator<Self::IntoIter>
+    where
+        Self: Sized,
+        Self::Item: Ord + Clone,
+    {
+        self.stateful_map(update_min_max as MinMaxFn<Self::Item>, None)
+    }
+}
+
+impl<I: IntoIterator> IntoRunningMinMaxIterator for I {}
+
+/// Addition which reports overflow rather than wrapping.
+pub trait CheckedAdd: Sized {
+    const ZERO: Self;
+
+    fn checked_add(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! impl_checked_add {
+    ($($t:ty),*) => {
+        $(
+            impl CheckedAdd for $t {
+                const ZERO: Self = 0;
+
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$t>::checked_add(self, other)
+                }
+            }
+        )*
+    };
+}
+
+impl_checked_add!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+type CumulativeSumFn<T> = fn(&mut Option<T>, T) -> Option<T>;
+
+pub type CumulativeSumIterator<I> =
+    StatefulMapIterator<I, Option<<I as Iterator>::Item>, CumulativeSumFn<<I as Iterator>::Item>>;
+
+fn add_checked<T: CheckedAdd + Copy>(total: &mut Option<T>, x: T) -> Option<T> {
+    *total = total.and_then(|total| total.checked_add(x));
+    *total
+}
+
+pub trait IntoCumulativeSumIterator: IntoIterator {
+    /// Yields the running total of the items. Once the total overflows, None is yielded for it
+    /// and every item after.
+    fn cumulative_sum(self) -> CumulativeSumIterator<Self::IntoIter>
+    where
+        Self: Sized,
+        Self::Item: CheckedAdd + Copy,
+    {
+        self.stateful_map(
+            add_checked as CumulativeSumFn<Self::Item>,
+            Some(Self::Item::ZERO),
+        )
+    }
+}
+
+impl<I: IntoIterator> IntoCumulativeSumIterator for I {}
+
+/// The items in the current window, along with their total.
+#[derive(Debug, Clone)]
+pub struct SlidingSumState<T> {
+    window: usize,
+    values: VecDeque<T>,
+    total: T,
+}
+
+type SlidingSumFn<T> = fn(&mut SlidingSumState<T>, T) -> Option<T>;
+
+pub type SlidingSumIterator<I> = Flatten<
+    StatefulMapIterator<
+        I,
+        SlidingSumState<<I as Iterator>::Item>,
+        SlidingSumFn<<I as Iterator>::Item>,
+    >,
+>;
+
+fn slide_sum<T>(state: &mut SlidingSumState<T>, x: T) -> Option<T>
+where
+    T: Add<Output = T> + Sub<Output = T> + Copy,
+{
+    state.total = state.total + x;
+    state.values.push_back(x);
+    if state.values.len() > state.window {
+        let leaving = state.values.pop_front().unwrap();
+        state.total = state.total - leaving;
+    }
+
+    (state.values.len() == state.window).then_some(state.total)
+}
+
+pub trait IntoSlidingSumIterator: IntoIterator {
+    /// Yields the sum of each full window of items, updating it as the window slides rather than
+    /// summing the whole window every time. For floats, rounding errors can build up over long
+    /// inputs.
+    fn sliding_sum(self, window: usize) -> SlidingSumIterator<Self::IntoIter>
+    where
+        Self: Sized,
+        Self::Item: Add<Output = Self::Item> + Sub<Output = Self::Item> + Copy + Default,
+    {
+        assert!(window > 0, "window size must be at least 1");
+        let state = SlidingSumState {
+            window,
+            values: VecDeque::with_capacity(window + 1),
+            total: Self::Item::default(),
+        };
+
+        self.stateful_map(slide_sum as SlidingSumFn<Self::Item>, state)
+            .flatten()
+    }
+}
+
+impl<I: IntoIterator> IntoSlidingSumIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct SharedStatefulMapIterator<I, S, F> {
+    shared: Arc<Mutex<S>>,
+    iter: I,
+    func: F,
+}
+
+impl<I: Iterator, S, F, FO> Iterator for SharedStatefulMapIterator<I, S, F>
+where
+    F: FnMut(&mut S, I::Item) -> FO,
+{
+    type Item = FO;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        let mut state = self.shared.lock().unwrap();
+        Some((self.func)(&mut state, item))
+    }
+}
+
+pub trait IntoSharedStatefulMapIterator: IntoIterator {
+    /// Like stateful_map, but the state is shared with other owners of the mutex, eg. other
+    /// pipelines. The lock is taken for each item, and only held while the function runs.
+    /// Panics if the mutex has been poisoned.
+    fn shared_stateful_map<S, F, FO>(
+        self,
+        shared: Arc<Mutex<S>>,
+        func: F,
+    ) -> SharedStatefulMapIterator<Self::IntoIter, S, F>
+    where
+        Self: Sized,
+        F: FnMut(&mut S, Self::Item) -> FO,
+    {
+        SharedStatefulMapIterator {
+            shared,
+            iter: self.into_iter(),
+            func,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoSharedStatefulMapIterator for I {}
+
 #[cfg(test)]
 mod tests {
-    use crate::stateful::IntoStatefulMapIterator;
+    use crate::stateful::{
+        IntoCumulativeSumIterator, IntoRunningFoldIterator, IntoRunningMinMaxIterator,
+        IntoSharedStatefulMapIterator, IntoSlidingSumIterator, IntoStatefulFinishMapIterator,
+        IntoStatefulIndexedMapIterator, IntoStatefulInspectMapIterator, IntoStatefulMapIterator,
+        IntoStatefulOkMapIterator, IntoStatefulPeekMapIterator, IntoStatefulResetMapIterator,
+        IntoStatefulTryMapIterator,
+    };
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn test_simple_stateful() {
@@ -99,4 +694,232 @@ mod tests {
             .collect::<Vec<_>>();
         println!("{:?}", mapped);
     }
+
+    #[test]
+    fn test_peek() {
+        #[derive(Debug)]
+        struct State {
+            count: usize,
+        }
+        fn next_is_larger(state: &mut State, x: u8, next: Option<&u8>) -> (u8, bool) {
+            state.count += 1;
+            (x, next.is_some_and(|n| *n > x))
+        }
+
+        let values = vec![1_u8, 3, 2, 2, 5];
+        let mapped = values
+            .stateful_map_peek(State { count: 0 }, next_is_larger)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            mapped,
+            [(1, true), (3, false), (2, false), (2, true), (5, false)]
+        );
+
+        let func = next_is_larger as fn(&mut State, u8, Option<&u8>) -> (u8, bool);
+        let iter = [1_u8, 3].stateful_map_peek(State { count: 0 }, func);
+        assert!(format!("{:?}", iter).starts_with("StatefulPeekMapIterator"));
+    }
+
+    #[test]
+    fn test_try_overflow() {
+        struct State {
+            total: u8,
+        }
+        fn checked_cumsum(state: &mut State, x: u8) -> Result<u8, String> {
+            state.total = state
+                .total
+                .checked_add(x)
+                .ok_or(format!("overflow adding {}", x))?;
+            Ok(state.total)
+        }
+
+        let values = vec![100_u8, 100, 100, 1];
+        let mapped = values
+            .stateful_try_map(State { total: 0 }, checked_cumsum)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            mapped,
+            [Ok(100), Ok(200), Err("overflow adding 100".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_map_ok() {
+        let values = [Ok(1), Err("x"), Ok(2)];
+        let mapped = values
+            .stateful_map_ok(0, |total, x| {
+                *total += x;
+                *total
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(mapped, [Ok(1), Err("x"), Ok(3)]);
+    }
+
+    #[test]
+    fn test_reset() {
+        struct State {
+            total: u32,
+        }
+        fn cumsum(state: &mut State, x: u32) -> u32 {
+            state.total += x;
+            state.total
+        }
+
+        let values = vec![1, 2, 0, 3, 4, 0, 5];
+        let mapped = values
+            .stateful_map_reset(|| State { total: 0 }, cumsum, |x| *x == 0)
+            .collect::<Vec<_>>();
+        assert_eq!(mapped, [1, 3, 0, 3, 7, 0, 5]);
+    }
+
+    #[test]
+    fn test_clone() {
+        #[derive(Clone)]
+        struct State {
+            total: u32,
+        }
+
+        let mut iter = (1..5).stateful_map(
+            |state: &mut State, x| {
+                state.total += x;
+                state.total
+            },
+            State { total: 0 },
+        );
+        iter.next();
+
+        let cloned = iter.clone();
+        assert_eq!(iter.collect::<Vec<_>>(), [3, 6, 10]);
+        assert_eq!(cloned.collect::<Vec<_>>(), [3, 6, 10]);
+    }
+
+    #[test]
+    fn test_running_fold() {
+        let sums = [1, 2, 3]
+            .running_fold(0, |acc, x| acc + x)
+            .collect::<Vec<_>>();
+        assert_eq!(sums, [0, 1, 3, 6]);
+
+        let sums = Vec::<i32>::new()
+            .running_fold(0, |acc, x| acc + x)
+            .collect::<Vec<_>>();
+        assert_eq!(sums, [0]);
+    }
+
+    #[test]
+    fn test_indexed() {
+        struct State {
+            total: usize,
+        }
+        fn weighted(state: &mut State, index: usize, x: usize) -> usize {
+            state.total += x;
+            index * x
+        }
+
+        let mut state = State { total: 0 };
+        let mapped = vec![5, 6, 7]
+            .stateful_map_indexed(&mut state, |state, i, x| weighted(state, i, x))
+            .collect::<Vec<_>>();
+        assert_eq!(mapped, [0, 6, 14]);
+        assert_eq!(state.total, 18);
+    }
+
+    #[test]
+    fn test_inspect() {
+        let mut snapshots = vec![];
+        let mapped = [1, 2, 3]
+            .stateful_map_inspect(
+                0,
+                |total, x| {
+                    *total += x;
+                    x * 10
+                },
+                |total| snapshots.push(*total),
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(mapped, [10, 20, 30]);
+        assert_eq!(snapshots, [1, 3, 6]);
+    }
+
+    #[test]
+    fn test_running_minmax() {
+        let mapped = [3, 1, 4, 1, 5].running_minmax().collect::<Vec<_>>();
+        assert_eq!(
+            mapped,
+            [(3, 3, 3), (1, 1, 3), (4, 1, 4), (1, 1, 4), (5, 1, 5)]
+        );
+    }
+
+    #[test]
+    fn test_cumulative_sum() {
+        let sums = [100_u8, 100, 50, 10, 1]
+            .cumulative_sum()
+            .collect::<Vec<_>>();
+        assert_eq!(sums, [Some(100), Some(200), Some(250), None, None]);
+
+        let sums = [-5_i32, 10, -20].cumulative_sum().collect::<Vec<_>>();
+        assert_eq!(sums, [Some(-5), Some(5), Some(-15)]);
+    }
+
+    #[test]
+    fn test_shared_stateful_map() {
+        let counter = Arc::new(Mutex::new(0));
+        fn count(total: &mut i32, x: char) -> (i32, char) {
+            *total += 1;
+            (*total, x)
+        }
+
+        let first = "ab"
+            .chars()
+            .shared_stateful_map(Arc::clone(&counter), count)
+            .collect::<Vec<_>>();
+        let second = "cd"
+            .chars()
+            .shared_stateful_map(Arc::clone(&counter), count)
+            .collect::<Vec<_>>();
+
+        assert_eq!(first, [(1, 'a'), (2, 'b')]);
+        assert_eq!(second, [(3, 'c'), (4, 'd')]);
+        assert_eq!(*counter.lock().unwrap(), 4);
+    }
+
+    #[test]
+    fn test_sliding_sum() {
+        let sums = [1, 2, 3, 4].sliding_sum(2).collect::<Vec<_>>();
+        assert_eq!(sums, [3, 5, 7]);
+
+        assert_eq!([1, 2, 3].sliding_sum(1).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!([1, 2, 3].sliding_sum(4).count(), 0);
+    }
+
+    #[test]
+    fn test_with_finish() {
+        struct State {
+            pending: Option<u8>,
+        }
+        fn pair_up(state: &mut State, x: u8) -> Vec<u8> {
+            match state.pending.take() {
+                Some(first) => vec![first, x],
+                None => {
+                    state.pending = Some(x);
+                    vec![]
+                }
+            }
+        }
+        fn flush(state: State) -> Option<Vec<u8>> {
+            state.pending.map(|x| vec![x])
+        }
+
+        let pairs = (1..=5)
+            .stateful_map_with_finish(State { pending: None }, pair_up, flush)
+            .filter(|pair| !pair.is_empty())
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, [vec![1, 2], vec![3, 4], vec![5]]);
+
+        let pairs = (1..=4)
+            .stateful_map_with_finish(State { pending: None }, pair_up, flush)
+            .filter(|pair| !pair.is_empty())
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, [vec![1, 2], vec![3, 4]]);
+    }
 }
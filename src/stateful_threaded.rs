@@ -1,10 +1,14 @@
+use std::iter::Flatten;
 use std::sync::mpsc::sync_channel;
 use std::sync::mpsc::Receiver;
 use std::sync::mpsc::SyncSender;
+use std::sync::Arc;
 use std::thread;
 use std::thread::available_parallelism;
 use std::thread::JoinHandle;
 
+use crate::threaded::output_index;
+
 #[derive(Debug)]
 struct StatefulWorker<I, O> {
     _handle: JoinHandle<()>,
@@ -62,10 +66,24 @@ where
     where
         F: FnMut(&mut S, FI) -> FO + Send + Copy + 'static,
         S: Send + Clone + 'static,
+    {
+        Self::with_state_fn(iter, func, move || state.clone())
+    }
+
+    /// Creates the iterator, building each worker's state with the given function.
+    fn with_state_fn<F, S, M>(
+        iter: I,
+        func: F,
+        make_state: M,
+    ) -> ThreadedStatefulIterator<I, FI, FO>
+    where
+        F: FnMut(&mut S, FI) -> FO + Send + Clone + 'static,
+        S: Send + 'static,
+        M: Fn() -> S,
     {
         let mut workers = vec![];
         for _ in 0..available_parallelism().unwrap().get() {
-            workers.push(StatefulWorker::new(func, state.clone()));
+            workers.push(StatefulWorker::new(func.clone(), make_state()));
         }
 
         let mut new_iter = Self {
@@ -94,7 +112,7 @@ where
 
     #[inline]
     fn output_index(&self) -> usize {
-        (2 * self.workers.len() + self.input_index - self.num_processing) % self.workers.len()
+        output_index(self.workers.len(), self.input_index, self.num_processing)
     }
 }
 
@@ -134,15 +152,79 @@ pub trait IntoStatefulThreadedIterator: IntoIterator {
     {
         ThreadedStatefulIterator::new(self.into_iter(), func, state)
     }
+
+    /// Like stateful_par_map, but each input can produce any number of outputs.
+    /// Outputs are flattened in the order of their inputs.
+    fn stateful_par_flat_map<F, FO, S>(
+        self,
+        func: F,
+        state: S,
+    ) -> Flatten<ThreadedStatefulIterator<Self::IntoIter, <Self as IntoIterator>::Item, Vec<FO>>>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(&mut S, <Self as IntoIterator>::Item) -> Vec<FO> + Send + Copy + 'static,
+        FO: Send + 'static,
+        S: Send + Clone + 'static,
+    {
+        ThreadedStatefulIterator::new(self.into_iter(), func, state).flatten()
+    }
+
+    /// Like stateful_par_flat_map, but each worker's state is built with make_state rather than
+    /// cloned, eg. to give every worker its own scratch buffer.
+    fn stateful_par_flat_map_init<F, FO, S, M>(
+        self,
+        make_state: M,
+        func: F,
+    ) -> Flatten<ThreadedStatefulIterator<Self::IntoIter, <Self as IntoIterator>::Item, Vec<FO>>>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(&mut S, <Self as IntoIterator>::Item) -> Vec<FO> + Send + Copy + 'static,
+        FO: Send + 'static,
+        S: Send + 'static,
+        M: Fn() -> S,
+    {
+        ThreadedStatefulIterator::with_state_fn(self.into_iter(), func, make_state).flatten()
+    }
+
+    /// Like stateful_par_map, but the function is also given a read-only resource shared between
+    /// all of the workers. Each worker's state is built with make_state.
+    fn stateful_par_map_shared<F, FO, S, R, M>(
+        self,
+        shared: Arc<R>,
+        make_state: M,
+        func: F,
+    ) -> ThreadedStatefulIterator<Self::IntoIter, <Self as IntoIterator>::Item, FO>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(&R, &mut S, <Self as IntoIterator>::Item) -> FO + Send + Copy + 'static,
+        FO: Send + 'static,
+        S: Send + 'static,
+        R: Send + Sync + 'static,
+        M: Fn() -> S,
+    {
+        ThreadedStatefulIterator::with_state_fn(
+            self.into_iter(),
+            move |state: &mut S, x| func(&shared, state, x),
+            make_state,
+        )
+    }
 }
 
 impl<I: IntoIterator> IntoStatefulThreadedIterator for I {}
 
 #[cfg(test)]
+// test_iterator_array only checks that mapping with a large state doesn't fall over
+#[allow(unused_variables)]
 mod tests {
 
     use super::StatefulWorker;
     use crate::stateful_threaded::IntoStatefulThreadedIterator;
+    use std::cell::Cell;
+    use std::sync::Arc;
+    use std::thread::available_parallelism;
 
     #[test]
     fn test_worker() {
@@ -209,4 +291,94 @@ mod tests {
             )
             .collect::<Vec<_>>();
     }
+
+    #[test]
+    fn test_iterator_array_output() {
+        #[derive(Clone)]
+        struct State {
+            buffer: Vec<u8>,
+        }
+        fn fill(state: &mut State, x: u8) -> u8 {
+            state.buffer.fill(x);
+            state.buffer.iter().max().copied().unwrap()
+        }
+
+        let mapped = (0..128)
+            .stateful_par_map(
+                fill,
+                State {
+                    buffer: vec![0; 1000],
+                },
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(mapped, (0..128).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_flat_map() {
+        #[derive(Clone)]
+        struct State {
+            buffer: Vec<usize>,
+        }
+        fn expand(state: &mut State, x: usize) -> Vec<usize> {
+            state.buffer.clear();
+            state.buffer.extend((0..x % 3).map(|i| x * 10 + i));
+            state.buffer.clone()
+        }
+
+        let values = (0..32).collect::<Vec<_>>();
+        let sequential = values
+            .iter()
+            .flat_map(|x| (0..x % 3).map(move |i| x * 10 + i))
+            .collect::<Vec<_>>();
+        let mapped = values
+            .stateful_par_flat_map(expand, State { buffer: vec![] })
+            .collect::<Vec<_>>();
+        assert_eq!(mapped, sequential);
+    }
+
+    #[test]
+    fn test_flat_map_init() {
+        let states_made = Cell::new(0);
+        let make_state = || {
+            states_made.set(states_made.get() + 1);
+            Vec::<usize>::with_capacity(2)
+        };
+        fn expand(buffer: &mut Vec<usize>, x: usize) -> Vec<usize> {
+            buffer.clear();
+            buffer.extend((0..x % 3).map(|i| x * 10 + i));
+            buffer.clone()
+        }
+
+        let sequential = (0..32)
+            .flat_map(|x| (0..x % 3).map(move |i| x * 10 + i))
+            .collect::<Vec<_>>();
+        let mapped = (0..32)
+            .stateful_par_flat_map_init(make_state, expand)
+            .collect::<Vec<_>>();
+        assert_eq!(mapped, sequential);
+        assert_eq!(states_made.get(), available_parallelism().unwrap().get());
+    }
+
+    #[test]
+    fn test_shared() {
+        let table = Arc::new([1, 10, 100, 1000]);
+        let states_made = Cell::new(0);
+        let make_state = || {
+            states_made.set(states_made.get() + 1);
+            Vec::<usize>::with_capacity(2)
+        };
+        fn lookup(table: &[usize; 4], scratch: &mut Vec<usize>, x: usize) -> usize {
+            scratch.clear();
+            scratch.extend([table[x % 4], x]);
+            scratch.iter().product()
+        }
+
+        let mapped = (0..32)
+            .stateful_par_map_shared(Arc::clone(&table), make_state, lookup)
+            .collect::<Vec<_>>();
+        let expected = (0..32).map(|x| table[x % 4] * x).collect::<Vec<_>>();
+        assert_eq!(mapped, expected);
+        assert_eq!(states_made.get(), available_parallelism().unwrap().get());
+    }
 }
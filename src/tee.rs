@@ -0,0 +1,106 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::{self, Debug, Formatter};
+use std::rc::Rc;
+
+#[derive(Debug)]
+struct TeeBuffer<I: Iterator> {
+    iter: I,
+    /// Items pulled by one side which the other side hasn't yielded yet
+    buffer: VecDeque<I::Item>,
+    /// Which side the buffered items are waiting for
+    waiting: bool,
+}
+
+/// One side of a tee. Items pulled by one side are buffered until the other side yields them
+/// too, so memory grows with how far apart the two sides are. It isn't Clone, since a copy of
+/// one side would buffer every item for a partner which never reads them.
+pub struct TeeIterator<I: Iterator> {
+    shared: Rc<RefCell<TeeBuffer<I>>>,
+    side: bool,
+}
+
+impl<I> Debug for TeeIterator<I>
+where
+    I: Iterator + Debug,
+    I::Item: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TeeIterator")
+            .field("shared", &self.shared)
+            .field("side", &self.side)
+            .finish()
+    }
+}
+
+impl<I> Iterator for TeeIterator<I>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut shared = self.shared.borrow_mut();
+        if shared.waiting == self.side {
+            if let Some(item) = shared.buffer.pop_front() {
+                return Some(item);
+            }
+        }
+
+        let item = shared.iter.next()?;
+        shared.buffer.push_back(item.clone());
+        shared.waiting = !self.side;
+
+        Some(item)
+    }
+}
+
+pub trait IntoTeeIterator: IntoIterator {
+    /// Splits the iterator into two, which each yield every item.
+    /// Items are buffered until both sides have seen them, so if one side gets far ahead of the
+    /// other, the buffer holds everything in between.
+    fn tee(self) -> (TeeIterator<Self::IntoIter>, TeeIterator<Self::IntoIter>)
+    where
+        Self: Sized,
+        Self::Item: Clone,
+    {
+        let shared = Rc::new(RefCell::new(TeeBuffer {
+            iter: self.into_iter(),
+            buffer: VecDeque::new(),
+            waiting: false,
+        }));
+
+        (
+            TeeIterator {
+                shared: Rc::clone(&shared),
+                side: false,
+            },
+            TeeIterator { shared, side: true },
+        )
+    }
+}
+
+impl<I: IntoIterator> IntoTeeIterator for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::IntoTeeIterator;
+
+    #[test]
+    fn test_tee() {
+        let (left, right) = (0..10).tee();
+        assert_eq!(left.collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+        assert_eq!(right.collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+
+        let (mut left, mut right) = (0..5).tee();
+        assert_eq!(left.next(), Some(0));
+        assert_eq!(left.next(), Some(1));
+        assert_eq!(right.next(), Some(0));
+        assert_eq!(right.next(), Some(1));
+        assert_eq!(right.next(), Some(2));
+        assert_eq!(left.collect::<Vec<_>>(), [2, 3, 4]);
+        assert!(format!("{:?}", right).starts_with("TeeIterator"));
+        assert_eq!(right.collect::<Vec<_>>(), [3, 4]);
+    }
+}
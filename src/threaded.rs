@@ -1,6 +1,30 @@
-use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::io::{self, Write};
+use std::iter::{Enumerate, Flatten, Take};
+use std::ops::{Deref, DerefMut};
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::{available_parallelism, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::batch::{BatchBySizeIterator, IntoBatchBySizeIterator};
+use crate::windows::{ContextWindowsIterator, IntoContextWindowsIterator};
+
+/// Index of the worker holding the oldest in-flight item, given that items are sent to the workers
+/// round-robin. That's the worker `num_processing` places before the next one to receive an input.
+#[inline]
+pub(crate) fn output_index(num_workers: usize, input_index: usize, num_processing: usize) -> usize {
+    if num_workers == 0 {
+        return 0;
+    }
+
+    (input_index % num_workers + num_workers - num_processing % num_workers) % num_workers
+}
 
 #[derive(Debug)]
 struct Worker<I, O> {
@@ -14,20 +38,32 @@ where
     I: Send + 'static,
     O: Send + 'static,
 {
+    /// Spawns the worker thread with the default builder.
+    #[cfg(test)]
     fn new<F>(func: F) -> Worker<I, O>
+    where
+        F: Fn(I) -> O + Send + 'static,
+    {
+        Self::with_builder(func, thread::Builder::new())
+    }
+
+    /// Spawns the worker thread with the given builder, eg. to set its stack size.
+    fn with_builder<F>(func: F, builder: thread::Builder) -> Worker<I, O>
     where
         F: Fn(I) -> O + Send + 'static,
     {
         let (input_sender, input_receiver) = sync_channel::<Option<I>>(1);
         let (output_sender, output_receiver) = sync_channel::<Option<O>>(0);
 
-        let handle = thread::spawn(move || {
-            for item in input_receiver {
-                if output_sender.send(item.map(&func)).is_err() {
-                    break;
+        let handle = builder
+            .spawn(move || {
+                for item in input_receiver {
+                    if output_sender.send(item.map(&func)).is_err() {
+                        break;
+                    }
                 }
-            }
-        });
+            })
+            .expect("failed to spawn worker thread");
 
         Self {
             _handle: handle,
@@ -37,12 +73,35 @@ where
     }
 }
 
+/// A panic caught while applying a function to an item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicError {
+    pub message: String,
+}
+
+impl From<Box<dyn Any + Send>> for PanicError {
+    fn from(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => "unknown panic".to_string(),
+            },
+        };
+
+        Self { message }
+    }
+}
+
+/// The workers only hold channel ends and thread handles, so the iterator is Send whenever the
+/// source, inputs and outputs are. It can be moved to another thread or nested in another par_map.
 #[derive(Debug)]
 pub struct ThreadedIterator<I: Iterator, FI, FO> {
     inner: I,
     workers: Vec<Worker<FI, FO>>,
     input_index: usize,
     num_processing: usize,
+    cancel: Option<Arc<AtomicBool>>,
 }
 
 impl<I, FI, FO> ThreadedIterator<I, FI, FO>
@@ -51,17 +110,32 @@ where
     FI: Send + 'static,
     FO: Send + 'static,
 {
+    /// The function is shared between the workers, so it only needs to be Sync rather than Copy.
     pub fn new<F>(iter: I, func: F) -> ThreadedIterator<I, FI, FO>
     where
-        F: Fn(FI) -> FO + Send + Copy + 'static,
+        F: Fn(FI) -> FO + Send + Sync + 'static,
+    {
+        Self::with_builders(iter, func, |_| thread::Builder::new())
+    }
+
+    /// Creates the iterator, spawning each worker thread with the builder made for its index.
+    fn with_builders<F, B>(iter: I, func: F, make_builder: B) -> ThreadedIterator<I, FI, FO>
+    where
+        F: Fn(FI) -> FO + Send + Sync + 'static,
+        B: Fn(usize) -> thread::Builder,
     {
+        let func = Arc::new(func);
         let mut new_iter = Self {
             inner: iter,
             workers: (0..available_parallelism().unwrap().get())
-                .map(|_| Worker::new(func))
+                .map(|i| {
+                    let func = Arc::clone(&func);
+                    Worker::with_builder(move |x| func(x), make_builder(i))
+                })
                 .collect(),
             input_index: 0,
             num_processing: 0,
+            cancel: None,
         };
 
         new_iter.fill_buffer();
@@ -69,9 +143,13 @@ where
         new_iter
     }
 
-    /// Fills the remaining space in the worker queue
+    /// Fills the remaining space in the worker queue.
+    /// Each worker has at most one item in flight, since num_processing never exceeds the number
+    /// of workers and items are handed out round-robin. A worker only gets its next item once its
+    /// previous output has been received, so its input channel always has space for it, and this
+    /// never blocks however slow the consumer is.
     fn fill_buffer(&mut self) {
-        while self.num_processing < self.workers.len() {
+        while self.num_processing < self.workers.len() && !self.is_cancelled() {
             let val = self.inner.next();
             self.workers[self.input_index].input.send(val).unwrap();
 
@@ -81,9 +159,69 @@ where
         }
     }
 
+    #[inline]
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+
+    /// Stops pulling from the source, and returns the outputs of the items that are already
+    /// being processed, in order. The workers are shut down afterwards.
+    pub fn drain(mut self) -> Vec<FO> {
+        let mut outputs = vec![];
+        while self.num_processing > 0 {
+            let val = self.workers[self.output_index()].output.recv().unwrap();
+            self.num_processing -= 1;
+            outputs.extend(val);
+        }
+
+        for worker in self.workers {
+            // Closing the input channel lets the worker thread finish
+            drop(worker.input);
+            let _ = worker._handle.join();
+        }
+
+        outputs
+    }
+
+    /// Collects the outputs in order until the deadline passes, then stops pulling from the
+    /// source and returns whatever was gathered. Items still being processed at the deadline are
+    /// dropped, and their workers exit once they finish rather than being waited on.
+    pub fn collect_timeout(mut self, deadline: Instant) -> Vec<FO> {
+        let mut outputs = vec![];
+        while self.num_processing > 0 {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.workers[self.output_index()]
+                .output
+                .recv_timeout(timeout)
+            {
+                Ok(Some(val)) => outputs.push(val),
+                // The source has run out, so everything after this is empty too
+                Ok(None) => break,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => panic!("worker threads have stopped"),
+            }
+            self.num_processing -= 1;
+
+            // Checked per item, since a slow source could otherwise hold us past the deadline
+            while self.num_processing < self.workers.len()
+                && !self.is_cancelled()
+                && Instant::now() < deadline
+            {
+                let val = self.inner.next();
+                self.workers[self.input_index].input.send(val).unwrap();
+                self.input_index = (self.input_index + 1) % self.workers.len();
+                self.num_processing += 1;
+            }
+        }
+
+        outputs
+    }
+
     #[inline]
     fn output_index(&self) -> usize {
-        (2 * self.workers.len() + self.input_index - self.num_processing) % self.workers.len()
+        output_index(self.workers.len(), self.input_index, self.num_processing)
     }
 }
 
@@ -96,6 +234,11 @@ where
     type Item = FO;
 
     fn next(&mut self) -> Option<Self::Item> {
+        // Only happens once cancelled and all the in-flight items are done
+        if self.num_processing == 0 {
+            return None;
+        }
+
         let val = self.workers[self.output_index()].output.recv().unwrap();
         self.num_processing -= 1;
 
@@ -105,48 +248,1807 @@ where
     }
 }
 
-pub trait IntoThreadedIterator: IntoIterator {
-    /// Creates a multithreaded iterator which applies the given function in parallel.
-    fn par_map<F, FO>(
-        self,
+/// Like ThreadedIterator, but the workers pull from a shared queue and the outputs are yielded
+/// as soon as they're done, along with the index of their input.
+#[derive(Debug)]
+pub struct UnorderedThreadedIterator<I: Iterator, FI, FO> {
+    inner: Enumerate<I>,
+    input: SyncSender<(usize, FI)>,
+    output: Receiver<(usize, FO)>,
+    handles: Vec<JoinHandle<()>>,
+    num_processing: usize,
+}
+
+impl<I, FI, FO> UnorderedThreadedIterator<I, FI, FO>
+where
+    I: Iterator<Item = FI>,
+    FI: Send + 'static,
+    FO: Send + 'static,
+{
+    pub fn new<F>(iter: I, func: F) -> UnorderedThreadedIterator<I, FI, FO>
+    where
+        F: Fn(FI) -> FO + Send + Sync + 'static,
+    {
+        let num_workers = available_parallelism().unwrap().get();
+        let (input_sender, input_receiver) = sync_channel::<(usize, FI)>(num_workers);
+        let (output_sender, output_receiver) = channel::<(usize, FO)>();
+
+        let handles = spawn_queue_workers(num_workers, func, input_receiver, output_sender);
+
+        let mut new_iter = Self {
+            inner: iter.enumerate(),
+            input: input_sender,
+            output: output_receiver,
+            handles,
+            num_processing: 0,
+        };
+
+        new_iter.fill_buffer();
+
+        new_iter
+    }
+
+    /// Keeps one item in flight per worker.
+    fn fill_buffer(&mut self) {
+        while self.num_processing < self.handles.len() {
+            let Some(item) = self.inner.next() else {
+                break;
+            };
+            self.input.send(item).unwrap();
+            self.num_processing += 1;
+        }
+    }
+}
+
+impl<I, FI, FO> Iterator for UnorderedThreadedIterator<I, FI, FO>
+where
+    I: Iterator<Item = FI>,
+    FI: Send + 'static,
+    FO: Send + 'static,
+{
+    type Item = (usize, FO);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.num_processing == 0 {
+            return None;
+        }
+
+        let val = self.output.recv().unwrap();
+        self.num_processing -= 1;
+
+        self.fill_buffer();
+
+        Some(val)
+    }
+}
+
+type SharedFn<FI, FO> = Arc<dyn Fn(FI) -> FO + Send + Sync>;
+
+/// An adaptive worker's channel. None tells the worker to shut down.
+type WorkerInput<FI> = Sender<Option<(usize, FI)>>;
+
+/// The adaptive workers which are waiting for an item, along with the count of live workers.
+#[derive(Debug)]
+struct PoolState<FI> {
+    idle: Vec<(usize, WorkerInput<FI>)>,
+    live: usize,
+    closed: bool,
+}
+
+/// Like ThreadedIterator, but the number of workers scales between a min and a max with the
+/// load. An item goes to an idle worker if there is one, or else to a newly spawned worker, and
+/// workers beyond the min shut themselves down after being idle for the timeout.
+pub struct AdaptiveThreadedIterator<I: Iterator, FI, FO> {
+    inner: Enumerate<I>,
+    func: SharedFn<FI, FO>,
+    pool: Arc<Mutex<PoolState<FI>>>,
+    output: Receiver<(usize, FO)>,
+    output_sender: Sender<(usize, FO)>,
+    /// Outputs which finished before the ones ahead of them
+    pending: BTreeMap<usize, FO>,
+    next_index: usize,
+    num_processing: usize,
+    next_worker_id: usize,
+    min_workers: usize,
+    max_workers: usize,
+    idle_timeout: Duration,
+}
+
+impl<I, FI, FO> AdaptiveThreadedIterator<I, FI, FO>
+where
+    I: Iterator<Item = FI>,
+    FI: Send + 'static,
+    FO: Send + 'static,
+{
+    /// At least one worker is always kept alive, so the pool never has to start from nothing.
+    pub fn new<F>(
+        iter: I,
         func: F,
-    ) -> ThreadedIterator<Self::IntoIter, <Self as IntoIterator>::Item, FO>
+        min_workers: usize,
+        max_workers: usize,
+        idle_timeout: Duration,
+    ) -> AdaptiveThreadedIterator<I, FI, FO>
     where
-        Self: Sized,
-        <Self as IntoIterator>::Item: Send + 'static,
-        F: Fn(<Self as IntoIterator>::Item) -> FO + Send + Copy + 'static,
-        FO: Send + 'static,
+        F: Fn(FI) -> FO + Send + Sync + 'static,
     {
-        ThreadedIterator::new(self.into_iter(), func)
+        let min_workers = min_workers.max(1);
+        let (output_sender, output_receiver) = channel();
+
+        let mut new_iter = Self {
+            inner: iter.enumerate(),
+            func: Arc::new(func),
+            pool: Arc::new(Mutex::new(PoolState {
+                idle: Vec::new(),
+                live: 0,
+                closed: false,
+            })),
+            output: output_receiver,
+            output_sender,
+            pending: BTreeMap::new(),
+            next_index: 0,
+            num_processing: 0,
+            next_worker_id: 0,
+            min_workers,
+            max_workers: max_workers.max(min_workers),
+            idle_timeout,
+        };
+
+        for _ in 0..min_workers {
+            new_iter.spawn_worker(None);
+        }
+        new_iter.fill_buffer();
+
+        new_iter
+    }
+
+    /// The number of worker threads currently alive.
+    pub fn num_workers(&self) -> usize {
+        self.pool.lock().unwrap().live
+    }
+
+    /// Spawns a worker, either starting on the given item or waiting in the idle list.
+    fn spawn_worker(&mut self, item: Option<(usize, FI)>) {
+        let func = Arc::clone(&self.func);
+        let pool = Arc::clone(&self.pool);
+        let output = self.output_sender.clone();
+        let (min_workers, idle_timeout) = (self.min_workers, self.idle_timeout);
+        let (input, receiver) = channel();
+        let id = self.next_worker_id;
+        self.next_worker_id += 1;
+
+        {
+            let mut state = pool.lock().unwrap();
+            state.live += 1;
+            if item.is_none() {
+                state.idle.push((id, input.clone()));
+            }
+        }
+
+        thread::spawn(move || {
+            let mut item = item;
+            loop {
+                if let Some((index, value)) = item.take() {
+                    let value = func(value);
+                    {
+                        // Back on the idle list before the output is sent, so the next item
+                        // can go to this worker rather than a new one
+                        let mut state = pool.lock().unwrap();
+                        if state.closed {
+                            break;
+                        }
+                        state.idle.push((id, input.clone()));
+                    }
+                    if output.send((index, value)).is_err() {
+                        break;
+                    }
+                }
+
+                match receiver.recv_timeout(idle_timeout) {
+                    Ok(Some(next)) => item = Some(next),
+                    Ok(None) | Err(RecvTimeoutError::Disconnected) => break,
+                    Err(RecvTimeoutError::Timeout) => {
+                        let mut state = pool.lock().unwrap();
+                        // Items are only sent with the lock held, so one which raced the
+                        // timeout is already waiting
+                        if let Ok(next) = receiver.try_recv() {
+                            match next {
+                                Some(next) => item = Some(next),
+                                None => break,
+                            }
+                        } else if state.live > min_workers {
+                            state.idle.retain(|(other, _)| *other != id);
+                            state.live -= 1;
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Keeps up to max_workers items in flight, spawning a worker when none are idle.
+    fn fill_buffer(&mut self) {
+        while self.num_processing < self.max_workers {
+            let Some(item) = self.inner.next() else {
+                break;
+            };
+            self.num_processing += 1;
+
+            let mut state = self.pool.lock().unwrap();
+            match state.idle.pop() {
+                Some((_, worker)) => worker.send(Some(item)).unwrap(),
+                None => {
+                    // Every live worker is busy with an item in flight, so there's room for
+                    // another under the max
+                    drop(state);
+                    self.spawn_worker(Some(item));
+                }
+            }
+        }
     }
 }
 
-impl<I: IntoIterator> IntoThreadedIterator for I {}
+impl<I: Iterator + Debug, FI, FO: Debug> Debug for AdaptiveThreadedIterator<I, FI, FO> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdaptiveThreadedIterator")
+            .field("inner", &self.inner)
+            .field("pending", &self.pending)
+            .field("next_index", &self.next_index)
+            .field("num_processing", &self.num_processing)
+            .field("min_workers", &self.min_workers)
+            .field("max_workers", &self.max_workers)
+            .field("idle_timeout", &self.idle_timeout)
+            .finish_non_exhaustive()
+    }
+}
 
-#[cfg(test)]
-mod tests {
-    use crate::threaded::{IntoThreadedIterator, Worker};
+impl<I: Iterator, FI, FO> Drop for AdaptiveThreadedIterator<I, FI, FO> {
+    fn drop(&mut self) {
+        let mut state = self.pool.lock().unwrap();
+        state.closed = true;
+        for (_, worker) in state.idle.drain(..) {
+            let _ = worker.send(None);
+        }
+    }
+}
 
-    #[test]
-    fn test_worker() {
-        fn square(x: i32) -> i32 {
-            x * x
+impl<I, FI, FO> Iterator for AdaptiveThreadedIterator<I, FI, FO>
+where
+    I: Iterator<Item = FI>,
+    FI: Send + 'static,
+    FO: Send + 'static,
+{
+    type Item = FO;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.num_processing == 0 {
+            return None;
         }
 
-        let pool = Worker::new(&square);
+        let val = loop {
+            if let Some(val) = self.pending.remove(&self.next_index) {
+                break val;
+            }
+            let (index, val) = self.output.recv().unwrap();
+            self.pending.insert(index, val);
+        };
+        self.next_index += 1;
+        self.num_processing -= 1;
 
-        pool.input.send(Some(10)).unwrap();
-        let res = pool.output.recv().unwrap();
-        assert_eq!(res, Some(100))
+        self.fill_buffer();
+
+        Some(val)
     }
+}
 
-    #[test]
-    fn test_iter() {
-        fn square(x: i32) -> i32 {
-            x * x
+type BufferPool<B> = Arc<Mutex<Vec<B>>>;
+
+/// A buffer borrowed from the pool of par_map_pooled. It's returned to the pool when dropped, so
+/// it can be reused for a later item.
+#[derive(Debug)]
+pub struct PooledBuffer<B> {
+    buffer: Option<B>,
+    pool: BufferPool<B>,
+    max_pooled: usize,
+}
+
+impl<B> PooledBuffer<B> {
+    /// Takes the buffer out of the pool for good.
+    pub fn into_inner(mut self) -> B {
+        self.buffer.take().unwrap()
+    }
+}
+
+impl<B> Deref for PooledBuffer<B> {
+    type Target = B;
+
+    fn deref(&self) -> &Self::Target {
+        self.buffer.as_ref().unwrap()
+    }
+}
+
+impl<B> DerefMut for PooledBuffer<B> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.buffer.as_mut().unwrap()
+    }
+}
+
+impl<B> Drop for PooledBuffer<B> {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            let mut pool = self.pool.lock().unwrap_or_else(|e| e.into_inner());
+            if pool.len() < self.max_pooled {
+                pool.push(buffer);
+            }
+        }
+    }
+}
+
+/// Spawns workers which take indexed items from a shared queue, sending each output back along
+/// with the index of its input.
+fn spawn_queue_workers<F, FI, FO>(
+    num_workers: usize,
+    func: F,
+    input: Receiver<(usize, FI)>,
+    output: Sender<(usize, FO)>,
+) -> Vec<JoinHandle<()>>
+where
+    F: Fn(FI) -> FO + Send + Sync + 'static,
+    FI: Send + 'static,
+    FO: Send + 'static,
+{
+    let func = Arc::new(func);
+    let input = Arc::new(Mutex::new(input));
+    (0..num_workers)
+        .map(|_| {
+            let func = Arc::clone(&func);
+            let input = Arc::clone(&input);
+            let output = output.clone();
+            thread::spawn(move || loop {
+                // The lock is released before running the function
+                let item = input.lock().unwrap().recv();
+                let Ok((index, item)) = item else {
+                    break;
+                };
+                if output.send((index, func(item))).is_err() {
+                    break;
+                }
+            })
+        })
+        .collect()
+}
+
+/// An output which wasn't ready within the timeout of par_map_slot_timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotTimeout;
+
+/// Like ThreadedIterator, but each output is only waited on for up to the timeout. Late outputs
+/// are yielded as errors, and their results are thrown away once the worker finishes them.
+#[derive(Debug)]
+pub struct SlotTimeoutIterator<I: Iterator, FI, FO> {
+    inner: Enumerate<I>,
+    input: Sender<(usize, FI)>,
+    output: Receiver<(usize, FO)>,
+    handles: Vec<JoinHandle<()>>,
+    /// Outputs which finished before the ones ahead of them
+    pending: BTreeMap<usize, FO>,
+    next_index: usize,
+    num_sent: usize,
+    /// Items sent to the workers whose output hasn't come back yet, including timed out ones
+    num_processing: usize,
+    timeout: Duration,
+}
+
+impl<I, FI, FO> SlotTimeoutIterator<I, FI, FO>
+where
+    I: Iterator<Item = FI>,
+    FI: Send + 'static,
+    FO: Send + 'static,
+{
+    pub fn new<F>(iter: I, func: F, timeout: Duration) -> SlotTimeoutIterator<I, FI, FO>
+    where
+        F: Fn(FI) -> FO + Send + Sync + 'static,
+    {
+        let num_workers = available_parallelism().unwrap().get();
+        Self::with_workers(iter, func, timeout, num_workers)
+    }
+
+    fn with_workers<F>(
+        iter: I,
+        func: F,
+        timeout: Duration,
+        num_workers: usize,
+    ) -> SlotTimeoutIterator<I, FI, FO>
+    where
+        F: Fn(FI) -> FO + Send + Sync + 'static,
+    {
+        let (input_sender, input_receiver) = channel();
+        let (output_sender, output_receiver) = channel();
+        let handles = spawn_queue_workers(num_workers, func, input_receiver, output_sender);
+
+        let mut new_iter = Self {
+            inner: iter.enumerate(),
+            input: input_sender,
+            output: output_receiver,
+            handles,
+            pending: BTreeMap::new(),
+            next_index: 0,
+            num_sent: 0,
+            num_processing: 0,
+            timeout,
+        };
+
+        new_iter.fill_buffer();
+
+        new_iter
+    }
+
+    /// Keeps one item in flight per worker. Returns false once the source has run out.
+    fn fill_buffer(&mut self) -> bool {
+        while self.num_processing < self.handles.len() {
+            let Some(item) = self.inner.next() else {
+                return false;
+            };
+            self.input.send(item).unwrap();
+            self.num_sent += 1;
+            self.num_processing += 1;
         }
 
-        let iter = (0..10).par_map(square).collect::<Vec<_>>();
-        assert_eq!(iter, [0, 1, 4, 9, 16, 25, 36, 49, 64, 81])
+        true
+    }
+
+    /// Stores an output which has come back, unless its slot has already timed out.
+    fn receive(&mut self, (index, val): (usize, FO)) {
+        self.num_processing -= 1;
+        if index >= self.next_index {
+            self.pending.insert(index, val);
+        }
+    }
+}
+
+impl<I, FI, FO> Iterator for SlotTimeoutIterator<I, FI, FO>
+where
+    I: Iterator<Item = FI>,
+    FI: Send + 'static,
+    FO: Send + 'static,
+{
+    type Item = Result<FO, SlotTimeout>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The slot's time starts now, even if its item can't be sent to a worker yet
+        let deadline = Instant::now() + self.timeout;
+        while self.next_index == self.num_sent {
+            if !self.fill_buffer() && self.next_index == self.num_sent {
+                return None;
+            }
+            if self.next_index == self.num_sent {
+                // Every worker is still busy with a timed out item
+                match self
+                    .output
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                {
+                    Ok(val) => self.receive(val),
+                    Err(RecvTimeoutError::Timeout) => {
+                        // No worker came free in time, so the slot's item is skipped
+                        self.inner.next()?;
+                        self.num_sent += 1;
+                        self.next_index += 1;
+                        return Some(Err(SlotTimeout));
+                    }
+                    Err(RecvTimeoutError::Disconnected) => panic!("worker threads have stopped"),
+                }
+            }
+        }
+
+        let val = loop {
+            if let Some(val) = self.pending.remove(&self.next_index) {
+                break Ok(val);
+            }
+            match self
+                .output
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            {
+                Ok(val) => self.receive(val),
+                Err(RecvTimeoutError::Timeout) => break Err(SlotTimeout),
+                Err(RecvTimeoutError::Disconnected) => panic!("worker threads have stopped"),
+            }
+        };
+        self.next_index += 1;
+
+        self.fill_buffer();
+
+        Some(val)
+    }
+}
+
+/// Picks how many items to send to a worker at once, based on the lower bound of the source's
+/// size hint. It aims for around 64 chunks per worker, so the work stays evenly spread while
+/// the cost of each channel send is shared between many items. Chunks are capped at 1024 items
+/// to keep latency and memory bounded, and small or unknown sources fall back to single items.
+fn tuned_chunk_size(size_hint: usize, num_workers: usize) -> usize {
+    (size_hint / (num_workers.max(1) * 64)).clamp(1, 1024)
+}
+
+/// The parallel prefix scan behind par_scan, with each chunk scanned on its own thread.
+fn scan_chunks<T, F>(mut items: Vec<T>, chunk_size: usize, identity: T, combine: &F) -> Vec<T>
+where
+    T: Send + Sync,
+    F: Fn(&T, &T) -> T + Sync,
+{
+    // Scan each chunk on its own
+    thread::scope(|scope| {
+        for chunk in items.chunks_mut(chunk_size) {
+            scope.spawn(move || {
+                for i in 1..chunk.len() {
+                    chunk[i] = combine(&chunk[i - 1], &chunk[i]);
+                }
+            });
+        }
+    });
+
+    // The total of everything before each chunk
+    let mut offsets = vec![identity];
+    for chunk in items.chunks(chunk_size) {
+        let total = combine(offsets.last().unwrap(), chunk.last().unwrap());
+        offsets.push(total);
+    }
+
+    thread::scope(|scope| {
+        for (chunk, offset) in items.chunks_mut(chunk_size).zip(&offsets).skip(1) {
+            scope.spawn(move || {
+                for item in chunk.iter_mut() {
+                    *item = combine(offset, item);
+                }
+            });
+        }
+    });
+
+    items
+}
+
+/// The bucketing behind par_bucket_concurrent, with each chunk bucketed on its own thread.
+fn bucket_chunks<T, F, const N: usize>(
+    items: Vec<T>,
+    chunk_size: usize,
+    key_func: &F,
+) -> [Vec<T>; N]
+where
+    T: Send,
+    F: Fn(&T) -> usize + Sync,
+{
+    let mut items = items.into_iter();
+    let chunk_buckets = thread::scope(|scope| {
+        let mut handles = vec![];
+        loop {
+            let chunk = items.by_ref().take(chunk_size).collect::<Vec<_>>();
+            if chunk.is_empty() {
+                break;
+            }
+            handles.push(scope.spawn(move || {
+                let mut buckets: [Vec<T>; N] = std::array::from_fn(|_| vec![]);
+                for item in chunk {
+                    buckets[key_func(&item)].push(item);
+                }
+                buckets
+            }));
+        }
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|e| resume_unwind(e)))
+            .collect::<Vec<_>>()
+    });
+
+    // Joining the chunks in order keeps each bucket in source order
+    let mut buckets: [Vec<T>; N] = std::array::from_fn(|_| vec![]);
+    for chunk in chunk_buckets {
+        for (bucket, items) in buckets.iter_mut().zip(chunk) {
+            bucket.extend(items);
+        }
+    }
+
+    buckets
+}
+
+/// Batches of a fixed number of items.
+type ChunksIterator<I, FI> = BatchBySizeIterator<I, fn(&FI) -> usize>;
+
+/// The chunked outputs of par_map_tuned.
+pub type TunedIterator<I, FI, FO> =
+    Flatten<ThreadedIterator<ChunksIterator<I, FI>, Vec<FI>, Vec<FO>>>;
+
+/// The sorted chunks of par_sort_chunks.
+pub type SortedChunksIterator<I, FI> = ThreadedIterator<ChunksIterator<I, FI>, Vec<FI>, Vec<FI>>;
+
+fn chunks<I: Iterator>(iter: I, chunk_size: usize) -> ChunksIterator<I, I::Item> {
+    iter.batch_by_size(chunk_size, |_| 1)
+}
+
+/// The successful outputs of par_map_split_errors.
+pub type OkIterator<I, FI, T> = Flatten<ThreadedIterator<I, FI, Option<T>>>;
+
+pub trait IntoThreadedIterator: IntoIterator {
+    /// Creates a multithreaded iterator which applies the given function in parallel.
+    fn par_map<F, FO>(
+        self,
+        func: F,
+    ) -> ThreadedIterator<Self::IntoIter, <Self as IntoIterator>::Item, FO>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> FO + Send + Sync + 'static,
+        FO: Send + 'static,
+    {
+        ThreadedIterator::new(self.into_iter(), func)
+    }
+
+    /// Like par_map, but also yields how long the function took on each item.
+    fn par_map_timed<F, FO>(
+        self,
+        func: F,
+    ) -> ThreadedIterator<Self::IntoIter, <Self as IntoIterator>::Item, (FO, Duration)>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> FO + Send + Sync + 'static,
+        FO: Send + 'static,
+    {
+        ThreadedIterator::new(self.into_iter(), move |x| {
+            let start = Instant::now();
+            let out = func(x);
+            (out, start.elapsed())
+        })
+    }
+
+    /// Like par_map, but yields each input alongside its output.
+    fn par_map_with_input<F, FO>(
+        self,
+        func: F,
+    ) -> ThreadedIterator<
+        Self::IntoIter,
+        <Self as IntoIterator>::Item,
+        (<Self as IntoIterator>::Item, FO),
+    >
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Clone + Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> FO + Send + Sync + 'static,
+        FO: Send + 'static,
+    {
+        ThreadedIterator::new(self.into_iter(), move |x| (x.clone(), func(x)))
+    }
+
+    /// Applies the function in parallel, writing each output on its own line in source order.
+    fn par_map_write<F, FO, W>(self, func: F, mut writer: W) -> io::Result<()>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> FO + Send + Sync + 'static,
+        FO: Display + Send + 'static,
+        W: Write,
+    {
+        for item in self.par_map(func) {
+            writeln!(writer, "{}", item)?;
+        }
+
+        writer.flush()
+    }
+
+    /// Like par_map, but panics in the function are caught and yielded as errors instead of
+    /// taking down the worker.
+    fn par_map_catch<F, FO>(
+        self,
+        func: F,
+    ) -> ThreadedIterator<Self::IntoIter, <Self as IntoIterator>::Item, Result<FO, PanicError>>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> FO + Send + Sync + 'static,
+        FO: Send + 'static,
+    {
+        ThreadedIterator::new(self.into_iter(), move |x| {
+            catch_unwind(AssertUnwindSafe(|| func(x))).map_err(PanicError::from)
+        })
+    }
+
+    /// Applies a fallible function in parallel. Ok values are yielded by the returned iterator,
+    /// while errors are sent to the returned receiver as soon as they happen.
+    fn par_map_split_errors<F, T, E>(
+        self,
+        func: F,
+    ) -> (
+        OkIterator<Self::IntoIter, <Self as IntoIterator>::Item, T>,
+        Receiver<E>,
+    )
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> Result<T, E> + Send + Sync + 'static,
+        T: Send + 'static,
+        E: Send + 'static,
+    {
+        let (error_sender, error_receiver) = channel();
+        let iter = ThreadedIterator::new(self.into_iter(), move |x| match func(x) {
+            Ok(val) => Some(val),
+            Err(e) => {
+                // The caller may not care about the errors, so a dropped receiver is fine
+                let _ = error_sender.send(e);
+                None
+            }
+        });
+
+        (iter.flatten(), error_receiver)
+    }
+
+    /// Like par_map, but stops pulling new items once the cancel flag is set.
+    /// Items already being processed are still yielded, then the iterator ends.
+    fn par_map_cancellable<F, FO>(
+        self,
+        func: F,
+        cancel: Arc<AtomicBool>,
+    ) -> ThreadedIterator<Self::IntoIter, <Self as IntoIterator>::Item, FO>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> FO + Send + Sync + 'static,
+        FO: Send + 'static,
+    {
+        let mut iter = ThreadedIterator::new(self.into_iter(), func);
+        iter.cancel = Some(cancel);
+        iter
+    }
+
+    /// Applies the function in parallel, returning the first Some output in source order. Once
+    /// it's found, no more items are pulled from the source.
+    fn par_find_map<F, R>(self, func: F) -> Option<R>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> Option<R> + Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        let mut iter = ThreadedIterator::new(self.into_iter(), func);
+        let found = iter.find_map(|x| x);
+        // Waits for the items still in flight, and shuts down the workers
+        iter.drain();
+
+        found
+    }
+
+    /// Like par_map, but each output is waited on for at most the timeout, so one slow item can't
+    /// hold up the rest for long. Late outputs are yielded as SlotTimeout errors; the worker
+    /// carries on with the item regardless, and its result is thrown away. If every worker is
+    /// still busy with a late item, the next slot's item is skipped once its timeout runs out.
+    fn par_map_slot_timeout<F, FO>(
+        self,
+        func: F,
+        timeout: Duration,
+    ) -> SlotTimeoutIterator<Self::IntoIter, <Self as IntoIterator>::Item, FO>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> FO + Send + Sync + 'static,
+        FO: Send + 'static,
+    {
+        SlotTimeoutIterator::new(self.into_iter(), func, timeout)
+    }
+
+    /// Like par_map followed by take, but the limit is applied to the source, so the workers
+    /// never pull ahead past the first k items.
+    fn par_map_take<F, FO>(
+        self,
+        func: F,
+        k: usize,
+    ) -> ThreadedIterator<Take<Self::IntoIter>, <Self as IntoIterator>::Item, FO>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> FO + Send + Sync + 'static,
+        FO: Send + 'static,
+    {
+        ThreadedIterator::new(self.into_iter().take(k), func)
+    }
+
+    /// Like par_map, but the worker threads are spawned with the given stack size in bytes.
+    /// Useful when the function recurses deeply or keeps large buffers on the stack.
+    fn par_map_with_stack<F, FO>(
+        self,
+        func: F,
+        stack_size: usize,
+    ) -> ThreadedIterator<Self::IntoIter, <Self as IntoIterator>::Item, FO>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> FO + Send + Sync + 'static,
+        FO: Send + 'static,
+    {
+        ThreadedIterator::with_builders(self.into_iter(), func, |_| {
+            thread::Builder::new().stack_size(stack_size)
+        })
+    }
+
+    /// Like par_map, but the worker threads are named `{name_prefix}-{i}`, so they can be told
+    /// apart in profilers and crash dumps.
+    fn par_map_named<F, FO>(
+        self,
+        name_prefix: &str,
+        func: F,
+    ) -> ThreadedIterator<Self::IntoIter, <Self as IntoIterator>::Item, FO>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> FO + Send + Sync + 'static,
+        FO: Send + 'static,
+    {
+        ThreadedIterator::with_builders(self.into_iter(), func, |i| {
+            thread::Builder::new().name(format!("{name_prefix}-{i}"))
+        })
+    }
+
+    /// Applies the function in parallel to each item along with up to `context` items before it.
+    /// The windows are built on the calling thread, cloning the items into each one.
+    fn par_map_windowed<F, FO>(
+        self,
+        context: usize,
+        func: F,
+    ) -> ThreadedIterator<
+        ContextWindowsIterator<Self::IntoIter>,
+        Vec<<Self as IntoIterator>::Item>,
+        FO,
+    >
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Clone + Send + 'static,
+        F: Fn(&[<Self as IntoIterator>::Item]) -> FO + Send + Sync + 'static,
+        FO: Send + 'static,
+    {
+        ThreadedIterator::new(self.context_windows(context), move |window: Vec<_>| {
+            func(&window)
+        })
+    }
+
+    /// Like par_map, but items are sent to the workers in chunks, sized from the source's size
+    /// hint (see tuned_chunk_size). This cuts the per-item overhead for large sources of cheap
+    /// items. Outputs are still yielded in order.
+    fn par_map_tuned<F, FO>(
+        self,
+        func: F,
+    ) -> TunedIterator<Self::IntoIter, <Self as IntoIterator>::Item, FO>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> FO + Send + Sync + 'static,
+        FO: Send + 'static,
+    {
+        let iter = self.into_iter();
+        let num_workers = available_parallelism().unwrap().get();
+        let chunk_size = tuned_chunk_size(iter.size_hint().0, num_workers);
+
+        ThreadedIterator::new(chunks(iter, chunk_size), move |chunk: Vec<_>| {
+            chunk.into_iter().map(&func).collect::<Vec<_>>()
+        })
+        .flatten()
+    }
+
+    /// Applies the function in parallel, yielding the outputs as soon as they're done rather than
+    /// in order. Each output comes with the index of the input it was made from.
+    fn par_map_indexed_unordered<F, FO>(
+        self,
+        func: F,
+    ) -> UnorderedThreadedIterator<Self::IntoIter, <Self as IntoIterator>::Item, FO>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> FO + Send + Sync + 'static,
+        FO: Send + 'static,
+    {
+        UnorderedThreadedIterator::new(self.into_iter(), func)
+    }
+
+    /// Like par_map, but scales the number of worker threads between min_workers and
+    /// max_workers with the load, shutting down workers which have been idle for the timeout.
+    /// Useful for long-lived pipelines which are quiet for much of the time.
+    fn par_map_adaptive<F, FO>(
+        self,
+        min_workers: usize,
+        max_workers: usize,
+        idle_timeout: Duration,
+        func: F,
+    ) -> AdaptiveThreadedIterator<Self::IntoIter, <Self as IntoIterator>::Item, FO>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> FO + Send + Sync + 'static,
+        FO: Send + 'static,
+    {
+        AdaptiveThreadedIterator::new(
+            self.into_iter(),
+            func,
+            min_workers,
+            max_workers,
+            idle_timeout,
+        )
+    }
+
+    /// Like par_map, but the function fills in a buffer rather than returning a new output. The
+    /// buffers are recycled once the consumer drops them, so only around one per worker is ever
+    /// made with make_buffer. Useful for large outputs, to save on allocations.
+    fn par_map_pooled<F, B, M>(
+        self,
+        make_buffer: M,
+        func: F,
+    ) -> ThreadedIterator<Self::IntoIter, <Self as IntoIterator>::Item, PooledBuffer<B>>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(&mut B, <Self as IntoIterator>::Item) + Send + Sync + 'static,
+        B: Send + 'static,
+        M: Fn() -> B + Send + Sync + 'static,
+    {
+        // In-flight outputs plus the one held by the consumer
+        let max_pooled = available_parallelism().unwrap().get() + 1;
+        let pool: BufferPool<B> = Arc::new(Mutex::new(Vec::with_capacity(max_pooled)));
+
+        ThreadedIterator::new(self.into_iter(), move |x| {
+            let recycled = pool.lock().unwrap().pop();
+            let mut buffer = recycled.unwrap_or_else(&make_buffer);
+            func(&mut buffer, x);
+
+            PooledBuffer {
+                buffer: Some(buffer),
+                pool: Arc::clone(&pool),
+                max_pooled,
+            }
+        })
+    }
+
+    /// Applies a filtering function in parallel, collecting the Some outputs keyed by the index of
+    /// their input.
+    fn par_map_sparse<F, FO>(self, func: F) -> BTreeMap<usize, FO>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> Option<FO> + Send + Sync + 'static,
+        FO: Send + 'static,
+    {
+        self.into_iter()
+            .enumerate()
+            .par_map(move |(index, x)| func(x).map(|out| (index, out)))
+            .flatten()
+            .collect()
+    }
+
+    /// Splits the items into chunks of chunk_size, and sorts each chunk in parallel. The chunks
+    /// are yielded in order, eg. to be merged back together with kmerge.
+    fn par_sort_chunks(
+        self,
+        chunk_size: usize,
+    ) -> SortedChunksIterator<Self::IntoIter, <Self as IntoIterator>::Item>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Ord + Send + 'static,
+    {
+        ThreadedIterator::new(chunks(self.into_iter(), chunk_size), |mut chunk: Vec<_>| {
+            chunk.sort();
+            chunk
+        })
+    }
+
+    /// Applies the function in parallel, collecting the outputs in order.
+    /// Rather than sending each item to a worker, the items are split into one contiguous chunk
+    /// per thread, so there's no per-item synchronisation. This makes it faster than
+    /// `par_map(func).collect()` for lots of cheap items, but nothing is yielded until the end.
+    fn par_map_collect<F, FO>(self, func: F) -> Vec<FO>
+    where
+        Self: Sized,
+        Self::IntoIter: ExactSizeIterator,
+        <Self as IntoIterator>::Item: Send,
+        F: Fn(<Self as IntoIterator>::Item) -> FO + Sync,
+        FO: Send,
+    {
+        let mut iter = self.into_iter();
+        let len = iter.len();
+        let num_threads = available_parallelism().unwrap().get();
+        let chunk_size = len.div_ceil(num_threads).max(1);
+
+        let func = &func;
+        thread::scope(|scope| {
+            let mut handles = vec![];
+            loop {
+                let chunk = iter.by_ref().take(chunk_size).collect::<Vec<_>>();
+                if chunk.is_empty() {
+                    break;
+                }
+                handles.push(scope.spawn(move || chunk.into_iter().map(func).collect::<Vec<_>>()));
+            }
+
+            let mut outputs = Vec::with_capacity(len);
+            for handle in handles {
+                outputs.extend(handle.join().unwrap_or_else(|e| resume_unwind(e)));
+            }
+            outputs
+        })
+    }
+
+    /// Like par_map_collect, but writes each output into the given slice at the index of its
+    /// input, rather than allocating a new vector.
+    /// Panics if the slice isn't the same length as the iterator.
+    fn par_map_into_slice<F, FO>(self, func: F, out: &mut [FO])
+    where
+        Self: Sized,
+        Self::IntoIter: ExactSizeIterator,
+        <Self as IntoIterator>::Item: Send,
+        F: Fn(<Self as IntoIterator>::Item) -> FO + Sync,
+        FO: Send,
+    {
+        let mut iter = self.into_iter();
+        let len = iter.len();
+        assert_eq!(
+            len,
+            out.len(),
+            "output slice length must match the number of items"
+        );
+        let num_threads = available_parallelism().unwrap().get();
+        let chunk_size = len.div_ceil(num_threads).max(1);
+
+        let func = &func;
+        thread::scope(|scope| {
+            let handles = out
+                .chunks_mut(chunk_size)
+                .map(|out_chunk| {
+                    let chunk = iter.by_ref().take(out_chunk.len()).collect::<Vec<_>>();
+                    scope.spawn(move || {
+                        for (slot, item) in out_chunk.iter_mut().zip(chunk) {
+                            *slot = func(item);
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            for handle in handles {
+                handle.join().unwrap_or_else(|e| resume_unwind(e));
+            }
+        })
+    }
+
+    /// Buckets the items in parallel, with each thread bucketing one contiguous chunk into its
+    /// own buckets, which are joined together at the end. Items stay in source order within each
+    /// bucket. Panics if the bucketing function gives an index of N or more.
+    fn par_bucket_concurrent<F, const N: usize>(
+        self,
+        key_func: F,
+    ) -> [Vec<<Self as IntoIterator>::Item>; N]
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send,
+        F: Fn(&<Self as IntoIterator>::Item) -> usize + Sync,
+    {
+        let items = self.into_iter().collect::<Vec<_>>();
+        let num_threads = available_parallelism().unwrap().get();
+        let chunk_size = items.len().div_ceil(num_threads).max(1);
+        bucket_chunks(items, chunk_size, &key_func)
+    }
+
+    /// Computes the inclusive prefix scan of the items in parallel, eg. the running totals.
+    /// The combine function must be associative, and identity must leave items unchanged when
+    /// combined with them. Each thread scans one contiguous chunk, then the chunks are offset by
+    /// the totals of the chunks before them.
+    fn par_scan<F>(
+        self,
+        identity: <Self as IntoIterator>::Item,
+        combine: F,
+    ) -> Vec<<Self as IntoIterator>::Item>
+    where
+        Self: Sized,
+        <Self as IntoIterator>::Item: Send + Sync,
+        F: Fn(
+                &<Self as IntoIterator>::Item,
+                &<Self as IntoIterator>::Item,
+            ) -> <Self as IntoIterator>::Item
+            + Sync,
+    {
+        let items = self.into_iter().collect::<Vec<_>>();
+        let num_threads = available_parallelism().unwrap().get();
+        let chunk_size = items.len().div_ceil(num_threads).max(1);
+        scan_chunks(items, chunk_size, identity, &combine)
+    }
+}
+
+impl<I: IntoIterator> IntoThreadedIterator for I {}
+
+pub trait MaybeParMap: IntoIterator {
+    /// Maps the function over the items either in parallel (with par_map) or sequentially (with
+    /// map), chosen at runtime. Useful for toggling parallelism without changing types.
+    fn maybe_par_map<F, FO>(self, parallel: bool, func: F) -> Box<dyn Iterator<Item = FO>>
+    where
+        Self: Sized,
+        Self::IntoIter: 'static,
+        <Self as IntoIterator>::Item: Send + 'static,
+        F: Fn(<Self as IntoIterator>::Item) -> FO + Send + Sync + 'static,
+        FO: Send + 'static,
+    {
+        if parallel {
+            Box::new(self.par_map(func))
+        } else {
+            Box::new(self.into_iter().map(func))
+        }
+    }
+}
+
+impl<I: IntoIterator> MaybeParMap for I {}
+
+/// Applies the function to every element in parallel, writing the results back into the same
+/// slice so that no output is allocated. Each thread handles one contiguous chunk.
+pub fn par_map_in_place<T, F>(data: &mut [T], func: F)
+where
+    T: Send,
+    F: Fn(&mut T) + Sync,
+{
+    let num_threads = available_parallelism().unwrap().get();
+    let chunk_size = data.len().div_ceil(num_threads).max(1);
+    map_chunks_in_place(data, chunk_size, &func);
+}
+
+/// The chunking behind par_map_in_place, with each chunk mapped on its own thread.
+fn map_chunks_in_place<T, F>(data: &mut [T], chunk_size: usize, func: &F)
+where
+    T: Send,
+    F: Fn(&mut T) + Sync,
+{
+    thread::scope(|scope| {
+        for chunk in data.chunks_mut(chunk_size) {
+            scope.spawn(move || chunk.iter_mut().for_each(func));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bucket::Bucket;
+    use crate::buffered::IntoBufferedIterator;
+    use crate::interleave::kmerge;
+    use crate::threaded::{
+        bucket_chunks, map_chunks_in_place, output_index, par_map_in_place, scan_chunks,
+        tuned_chunk_size, IntoThreadedIterator, MaybeParMap, PanicError, SlotTimeout,
+        SlotTimeoutIterator, Worker,
+    };
+    use std::collections::HashMap;
+    use std::collections::VecDeque;
+    use std::hint::black_box;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::thread::available_parallelism;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_output_index() {
+        // Simulate sending and receiving items round-robin, tracking which worker each in-flight
+        // item went to, and check against every reachable state.
+        for num_workers in 1..=6 {
+            let mut in_flight = VecDeque::new();
+            let mut input_index = 0;
+            for step in 0..100 {
+                let send = in_flight.is_empty() || (in_flight.len() < num_workers && step % 3 != 0);
+                if send {
+                    in_flight.push_back(input_index);
+                    input_index = (input_index + 1) % num_workers;
+                } else {
+                    let expected = in_flight.pop_front().unwrap();
+                    assert_eq!(
+                        output_index(num_workers, input_index, in_flight.len() + 1),
+                        expected
+                    );
+                }
+            }
+        }
+
+        // Matches plain modular arithmetic over all small inputs
+        for num_workers in 1..=8 {
+            for input_index in 0..num_workers {
+                for num_processing in 0..=num_workers {
+                    let expected = (input_index as isize - num_processing as isize)
+                        .rem_euclid(num_workers as isize)
+                        as usize;
+                    assert_eq!(
+                        output_index(num_workers, input_index, num_processing),
+                        expected
+                    );
+                }
+            }
+        }
+
+        assert_eq!(output_index(0, 0, 0), 0);
+    }
+
+    #[test]
+    fn test_worker() {
+        fn square(x: i32) -> i32 {
+            x * x
+        }
+
+        let pool = Worker::new(&square);
+
+        pool.input.send(Some(10)).unwrap();
+        let res = pool.output.recv().unwrap();
+        assert_eq!(res, Some(100))
+    }
+
+    #[test]
+    fn test_iter() {
+        fn square(x: i32) -> i32 {
+            x * x
+        }
+
+        let iter = (0..10).par_map(square).collect::<Vec<_>>();
+        assert_eq!(iter, [0, 1, 4, 9, 16, 25, 36, 49, 64, 81])
+    }
+
+    #[test]
+    fn test_timed() {
+        fn slow_square(x: i32) -> i32 {
+            thread::sleep(Duration::from_millis(20));
+            x * x
+        }
+
+        let (values, durations): (Vec<_>, Vec<_>) = (0..4).par_map_timed(slow_square).unzip();
+        assert_eq!(values, [0, 1, 4, 9]);
+        assert!(durations.iter().all(|d| *d >= Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn test_with_input() {
+        let pairs = (0..5).par_map_with_input(|x| x * x).collect::<Vec<_>>();
+        assert_eq!(pairs, [(0, 0), (1, 1), (2, 4), (3, 9), (4, 16)]);
+    }
+
+    #[test]
+    fn test_write() {
+        let mut out = vec![];
+        (0..5).par_map_write(|x| x * x, &mut out).unwrap();
+        assert_eq!(out, b"0\n1\n4\n9\n16\n");
+    }
+
+    #[test]
+    fn test_maybe_par() {
+        fn square(x: i32) -> i32 {
+            x * x
+        }
+
+        let sequential = (0..10).maybe_par_map(false, square).collect::<Vec<_>>();
+        let parallel = (0..10).maybe_par_map(true, square).collect::<Vec<_>>();
+        assert_eq!(sequential, parallel);
+        assert_eq!(parallel, [0, 1, 4, 9, 16, 25, 36, 49, 64, 81]);
+    }
+
+    #[test]
+    fn test_shared_resource() {
+        let table = Arc::new((0..10).map(|x| (x, x * 100)).collect::<HashMap<_, _>>());
+
+        let lookup = {
+            let table = Arc::clone(&table);
+            move |x: i32| table[&x]
+        };
+        let iter = (0..10).rev().par_map(lookup);
+
+        // Only the one closure holds a reference, however many workers there are
+        assert_eq!(Arc::strong_count(&table), 2);
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            [900, 800, 700, 600, 500, 400, 300, 200, 100, 0]
+        );
+    }
+
+    #[test]
+    fn test_catch() {
+        fn checked_square(x: i32) -> i32 {
+            if x == 3 {
+                panic!("bad input {}", x);
+            }
+            x * x
+        }
+
+        let results = (0..6).par_map_catch(checked_square).collect::<Vec<_>>();
+        assert_eq!(
+            results,
+            [
+                Ok(0),
+                Ok(1),
+                Ok(4),
+                Err(PanicError {
+                    message: "bad input 3".to_string()
+                }),
+                Ok(16),
+                Ok(25)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_errors() {
+        fn checked_half(x: i32) -> Result<i32, String> {
+            match x % 2 {
+                0 => Ok(x / 2),
+                _ => Err(format!("{} is odd", x)),
+            }
+        }
+
+        let (oks, errors) = (0..6).par_map_split_errors(checked_half);
+        assert_eq!(oks.collect::<Vec<_>>(), [0, 1, 2]);
+        assert_eq!(
+            errors.try_iter().collect::<Vec<_>>(),
+            ["1 is odd", "3 is odd", "5 is odd"]
+        );
+    }
+
+    #[test]
+    fn test_drain() {
+        fn square(x: i32) -> i32 {
+            x * x
+        }
+
+        let mut iter = (0..1000).par_map(square);
+        let taken = (0..3).map(|_| iter.next().unwrap()).collect::<Vec<_>>();
+        assert_eq!(taken, [0, 1, 4]);
+
+        // Only the items already sent to a worker come back
+        let num_workers = available_parallelism().unwrap().get() as i32;
+        let drained = iter.drain();
+        assert_eq!(
+            drained,
+            (3..3 + num_workers).map(square).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_collect_timeout() {
+        // A finite source finishes well before the deadline
+        let outputs = (0..10)
+            .par_map(|x| x * 2)
+            .collect_timeout(Instant::now() + Duration::from_secs(10));
+        assert_eq!(outputs, (0..10).map(|x| x * 2).collect::<Vec<_>>());
+
+        // Items from 5 on are held until after the deadline, which is generous enough for the
+        // rest to make it
+        let (release, gate) = channel::<()>();
+        let gate = Mutex::new(gate);
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let outputs = (0..)
+            .par_map(move |x| {
+                if x >= 5 {
+                    let _ = gate.lock().unwrap().recv();
+                }
+                x * 2
+            })
+            .collect_timeout(deadline);
+        assert!(Instant::now() >= deadline);
+        drop(release);
+
+        assert_eq!(outputs, [0, 2, 4, 6, 8]);
+    }
+
+    #[test]
+    fn test_cancellable() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut iter = (0..1_000_000).par_map_cancellable(|x| x * 2, Arc::clone(&cancel));
+        for _ in 0..5 {
+            iter.next();
+        }
+
+        cancel.store(true, Ordering::Relaxed);
+        let num_workers = available_parallelism().unwrap().get();
+        assert!(iter.count() <= num_workers);
+    }
+
+    #[test]
+    fn test_find_map() {
+        let threshold = 5000;
+        let found = (0..1000).par_find_map(move |x| (x * x > threshold).then_some((x, x * x)));
+        let expected = (0..1000).find_map(|x| (x * x > threshold).then_some((x, x * x)));
+        assert_eq!(found, expected);
+        assert_eq!(found, Some((71, 5041)));
+
+        let evaluated = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&evaluated);
+        let found = (0..1000).par_find_map(move |x| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            (x == 10).then_some(x)
+        });
+        assert_eq!(found, Some(10));
+        let num_workers = available_parallelism().unwrap().get();
+        assert!(evaluated.load(Ordering::SeqCst) <= 11 + num_workers);
+
+        assert_eq!((0..100).par_find_map(|_| None::<i32>), None);
+    }
+
+    #[test]
+    fn test_slot_timeout() {
+        // Item 2 is held until its slot has timed out, so the result doesn't depend on timing
+        let (release, gate) = channel::<()>();
+        let gate = Mutex::new(gate);
+        let mut outputs = (0..5).par_map_slot_timeout(
+            move |x| {
+                if x == 2 {
+                    let _ = gate.lock().unwrap().recv();
+                }
+                x + 1
+            },
+            Duration::from_secs(1),
+        );
+        assert_eq!(outputs.next(), Some(Ok(1)));
+        assert_eq!(outputs.next(), Some(Ok(2)));
+        assert_eq!(outputs.next(), Some(Err(SlotTimeout)));
+        drop(release);
+        assert_eq!(outputs.collect::<Vec<_>>(), [Ok(4), Ok(5)]);
+
+        let outputs = (0..20)
+            .par_map_slot_timeout(|x| x * 2, Duration::from_secs(10))
+            .collect::<Vec<_>>();
+        assert_eq!(outputs, (0..20).map(|x| Ok(x * 2)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_slot_timeout_all_workers_busy() {
+        // The only worker is held on item 0, so slot 1 can't even be started
+        let (release, gate) = channel::<()>();
+        let gate = Mutex::new(gate);
+        let mut outputs = SlotTimeoutIterator::with_workers(
+            0..4,
+            move |x| {
+                if x == 0 {
+                    let _ = gate.lock().unwrap().recv();
+                }
+                x + 1
+            },
+            Duration::from_secs(1),
+            1,
+        );
+        assert_eq!(outputs.next(), Some(Err(SlotTimeout)));
+        assert_eq!(outputs.next(), Some(Err(SlotTimeout)));
+        drop(release);
+        assert_eq!(outputs.collect::<Vec<_>>(), [Ok(3), Ok(4)]);
+    }
+
+    #[test]
+    fn test_take() {
+        let pulled = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&pulled);
+        let source = (0..1_000_000).inspect(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let outputs = source.par_map_take(|x| x * 2, 5).collect::<Vec<_>>();
+        assert_eq!(outputs, [0, 2, 4, 6, 8]);
+        let num_workers = available_parallelism().unwrap().get();
+        assert!(pulled.load(Ordering::SeqCst) <= 5 + num_workers);
+    }
+
+    #[test]
+    fn test_backpressure() {
+        // Fails rather than hanging if the pipeline deadlocks
+        let (done_sender, done_receiver) = channel();
+        thread::spawn(move || {
+            for depth in [0, 1, 2, 8] {
+                let outputs = (0..200)
+                    .prefetch(depth)
+                    .par_map(|x| x * 2)
+                    .buffered(depth)
+                    .enumerate()
+                    .map(|(i, x)| {
+                        // A consumer which is slow every so often
+                        if i % 50 == 0 {
+                            thread::sleep(Duration::from_millis(20));
+                        }
+                        x
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(outputs, (0..200).map(|x| x * 2).collect::<Vec<_>>());
+            }
+            done_sender.send(()).unwrap();
+        });
+
+        done_receiver
+            .recv_timeout(Duration::from_secs(30))
+            .expect("pipeline deadlocked or panicked");
+    }
+
+    #[test]
+    fn test_with_stack() {
+        fn big_stack(x: u8) -> usize {
+            // Bigger than the default 2MiB thread stack
+            let buffer = black_box([x; 8 * 1024 * 1024]);
+            buffer.iter().map(|b| *b as usize).sum()
+        }
+
+        let sums = (0..3)
+            .par_map_with_stack(big_stack, 64 * 1024 * 1024)
+            .collect::<Vec<_>>();
+        assert_eq!(sums, [0, 8 * 1024 * 1024, 16 * 1024 * 1024]);
+    }
+
+    #[test]
+    fn test_named() {
+        let names = (0..10)
+            .par_map_named("mapper", |_| thread::current().name().map(String::from))
+            .collect::<Vec<_>>();
+        assert_eq!(names.len(), 10);
+        for name in names {
+            let name = name.expect("worker thread should be named");
+            let index = name.strip_prefix("mapper-").unwrap();
+            assert!(index.parse::<usize>().is_ok(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_windowed() {
+        let values = (0..20).map(|x| (x * 7) % 11).collect::<Vec<_>>();
+        let sequential = (0..values.len())
+            .map(|i| values[i.saturating_sub(2)..=i].iter().sum::<i32>())
+            .collect::<Vec<_>>();
+
+        let parallel = values
+            .par_map_windowed(2, |window| window.iter().sum::<i32>())
+            .collect::<Vec<_>>();
+        assert_eq!(parallel, sequential);
+    }
+
+    #[test]
+    fn test_collect() {
+        fn square(x: u32) -> u64 {
+            x as u64 * x as u64
+        }
+
+        let sequential = (0..100_000).map(square).collect::<Vec<_>>();
+        let collected = (0..100_000).par_map_collect(square);
+        let mapped = (0..100_000).par_map(square).collect::<Vec<_>>();
+
+        assert_eq!(collected, sequential);
+        assert_eq!(mapped, sequential);
+
+        assert!(Vec::<u32>::new().par_map_collect(square).is_empty());
+    }
+
+    #[test]
+    fn test_into_slice() {
+        let mut out = [0; 10];
+        (0..10_u32).par_map_into_slice(|x| x * x, &mut out);
+        assert_eq!(out, [0, 1, 4, 9, 16, 25, 36, 49, 64, 81]);
+
+        let mut empty: [u32; 0] = [];
+        (0..0_u32).par_map_into_slice(|x| x, &mut empty);
+    }
+
+    #[test]
+    #[should_panic(expected = "output slice length")]
+    fn test_into_slice_mismatch() {
+        let mut out = [0; 3];
+        (0..10_u32).par_map_into_slice(|x| x, &mut out);
+    }
+
+    #[test]
+    fn test_adaptive() {
+        let (release, gate) = channel::<()>();
+        let gate = Mutex::new(gate);
+        let mut iter = (0..100).par_map_adaptive(1, 4, Duration::from_millis(50), move |x| {
+            if x < 4 {
+                let _ = gate.lock().unwrap().recv();
+            }
+            x * 2
+        });
+
+        // Every worker is held up, so the pool has grown to the max
+        assert_eq!(iter.num_workers(), 4);
+        assert!(format!("{iter:?}").starts_with("AdaptiveThreadedIterator"));
+        drop(release);
+        for i in 0..20 {
+            assert_eq!(iter.next(), Some(i * 2));
+        }
+
+        // Once idle, the extra workers shut down
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while iter.num_workers() > 1 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(iter.num_workers(), 1);
+
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            (20..100).map(|x| x * 2).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_adaptive_scale_down() {
+        let (release, gate) = channel::<()>();
+        let gate = Mutex::new(gate);
+        let mut iter = (0..100).par_map_adaptive(2, 4, Duration::from_millis(50), move |x| {
+            if x < 4 {
+                let _ = gate.lock().unwrap().recv();
+            }
+            x * 2
+        });
+        assert_eq!(iter.num_workers(), 4);
+        drop(release);
+        assert_eq!(iter.by_ref().count(), 100);
+
+        // The pool shrinks back to the min, but no further
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while iter.num_workers() > 2 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(iter.num_workers(), 2);
+    }
+
+    #[test]
+    fn test_send() {
+        fn assert_send<T: Send>(value: T) -> T {
+            value
+        }
+
+        let iter = assert_send((0..10).par_map(|x: i32| x + 1));
+        let handle = thread::spawn(move || iter.collect::<Vec<_>>());
+        assert_eq!(handle.join().unwrap(), (1..11).collect::<Vec<_>>());
+
+        let nested = (0..4)
+            .par_map(|x: i32| (0..x).par_map(|y| y * 2).sum::<i32>())
+            .collect::<Vec<_>>();
+        assert_eq!(nested, [0, 0, 2, 6]);
+
+        assert_send((0..10).par_map_indexed_unordered(|x: i32| x));
+        assert_send((0..10).par_map_adaptive(1, 2, Duration::from_millis(10), |x: i32| x));
+    }
+
+    #[test]
+    fn test_sparse() {
+        let squares = (0..10).par_map_sparse(|x| (x % 2 == 0).then(|| x * x));
+        assert_eq!(
+            squares.into_iter().collect::<Vec<_>>(),
+            [(0, 0), (2, 4), (4, 16), (6, 36), (8, 64)]
+        );
+    }
+
+    #[test]
+    fn test_bucket_concurrent() {
+        let values = (0..100).map(|x| (x * 37) % 101).collect::<Vec<_>>();
+        let expected = values
+            .clone()
+            .into_iter()
+            .try_bucket_arr::<_, 3>(|x| x % 3)
+            .unwrap();
+
+        let buckets = values.clone().par_bucket_concurrent::<_, 3>(|x| x % 3);
+        assert_eq!(buckets, expected);
+
+        // Several chunks, including a short one at the end
+        let buckets = bucket_chunks::<_, _, 3>(values, 7, &|x| x % 3);
+        assert_eq!(buckets, expected);
+    }
+
+    #[test]
+    fn test_map_in_place() {
+        let mut data = vec![1, 2, 3, 4];
+        par_map_in_place(&mut data, |x| *x *= 2);
+        assert_eq!(data, [2, 4, 6, 8]);
+
+        // Uneven chunks, each on its own thread
+        let mut data = (0..10).collect::<Vec<_>>();
+        map_chunks_in_place(&mut data, 3, &|x: &mut i32| *x += 1);
+        assert_eq!(data, (1..11).collect::<Vec<_>>());
+
+        let mut data = Vec::<i32>::new();
+        par_map_in_place(&mut data, |x| *x *= 2);
+        assert!(data.is_empty());
+    }
+
+    #[test]
+    fn test_scan() {
+        let sums = (0..1000_u64).par_scan(0, |a, b| a + b);
+        let expected = (0..1000_u64)
+            .scan(0, |total, x| {
+                *total += x;
+                Some(*total)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sums, expected);
+
+        // Not commutative, so the chunks must be combined in order
+        let joined = ["a", "b", "c", "d", "e"]
+            .map(String::from)
+            .par_scan(String::new(), |a, b| format!("{a}{b}"));
+        assert_eq!(joined, ["a", "ab", "abc", "abcd", "abcde"]);
+
+        assert!(Vec::<u64>::new().par_scan(0, |a, b| a + b).is_empty());
+
+        // Several chunks, including a short one at the end
+        let sums = scan_chunks((0..1000_u64).collect(), 7, 0, &|a, b| a + b);
+        assert_eq!(sums, expected);
+        let joined = scan_chunks(
+            ["a", "b", "c", "d", "e"].map(String::from).to_vec(),
+            2,
+            String::new(),
+            &|a, b| format!("{a}{b}"),
+        );
+        assert_eq!(joined, ["a", "ab", "abc", "abcd", "abcde"]);
+    }
+
+    #[test]
+    fn test_pooled() {
+        let allocations = Arc::new(AtomicUsize::new(0));
+        let make_buffer = {
+            let allocations = Arc::clone(&allocations);
+            move || {
+                allocations.fetch_add(1, Ordering::SeqCst);
+                vec![0_u32; 1024]
+            }
+        };
+
+        let totals = (0..100_u32)
+            .par_map_pooled(make_buffer, |buffer, x| buffer.fill(x))
+            .map(|buffer| buffer.iter().sum::<u32>())
+            .collect::<Vec<_>>();
+        assert_eq!(totals, (0..100).map(|x| x * 1024).collect::<Vec<_>>());
+
+        let num_workers = available_parallelism().unwrap().get();
+        assert!(allocations.load(Ordering::SeqCst) <= num_workers + 1);
+
+        // Taking a buffer keeps it out of the pool
+        let buffer = (0..1).par_map_pooled(Vec::new, |b, x| b.push(x)).next();
+        assert_eq!(buffer.unwrap().into_inner(), [0]);
+    }
+
+    #[test]
+    fn test_sort_chunks() {
+        let chunks = [5, 3, 1, 4, 2, 0].par_sort_chunks(3).collect::<Vec<_>>();
+        assert_eq!(chunks, [[1, 3, 5], [0, 2, 4]]);
+
+        let values = (0..100).map(|x| (x * 37) % 101).collect::<Vec<_>>();
+        let merged = kmerge(values.clone().par_sort_chunks(8).collect()).collect::<Vec<_>>();
+        let mut expected = values;
+        expected.sort();
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn test_tuned() {
+        assert_eq!(tuned_chunk_size(0, 4), 1);
+        assert_eq!(tuned_chunk_size(100, 4), 1);
+        assert_eq!(tuned_chunk_size(256_000, 4), 1000);
+        assert_eq!(tuned_chunk_size(usize::MAX, 4), 1024);
+
+        let mapped = (0..200_000_u64)
+            .par_map_tuned(|x| x * 3)
+            .collect::<Vec<_>>();
+        assert_eq!(mapped, (0..200_000).map(|x| x * 3).collect::<Vec<_>>());
+
+        // No size hint to go on
+        let mapped = (0..100).filter(|x| x % 2 == 0).par_map_tuned(|x| x + 1);
+        assert_eq!(
+            mapped.collect::<Vec<_>>(),
+            (0..100)
+                .filter(|x| x % 2 == 0)
+                .map(|x| x + 1)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_indexed_unordered() {
+        let delays = [30, 0, 20, 5, 10, 0, 25, 15];
+        let mut outputs = delays
+            .par_map_indexed_unordered(|ms| {
+                thread::sleep(Duration::from_millis(ms));
+                ms * 2
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(outputs.len(), delays.len());
+
+        outputs.sort();
+        for (i, (index, out)) in outputs.into_iter().enumerate() {
+            assert_eq!(index, i);
+            assert_eq!(out, delays[i] * 2);
+        }
+
+        assert_eq!(Vec::<u8>::new().par_map_indexed_unordered(|x| x).count(), 0);
     }
 }
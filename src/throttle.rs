@@ -0,0 +1,140 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+pub struct ThrottleIterator<I> {
+    iter: I,
+    min_interval: Duration,
+    last: Option<Instant>,
+}
+
+impl<I: Iterator> Iterator for ThrottleIterator<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(last) = self.last {
+            // No sleeping if the consumer is already slower than the interval
+            if let Some(remaining) = self.min_interval.checked_sub(last.elapsed()) {
+                thread::sleep(remaining);
+            }
+        }
+
+        let item = self.iter.next()?;
+        self.last = Some(Instant::now());
+
+        Some(item)
+    }
+}
+
+pub trait IntoThrottleIterator: IntoIterator {
+    /// Rate limits the iterator, sleeping so that at least min_interval passes between items
+    /// being pulled from the source.
+    fn throttle(self, min_interval: Duration) -> ThrottleIterator<Self::IntoIter>
+    where
+        Self: Sized,
+    {
+        ThrottleIterator {
+            iter: self.into_iter(),
+            min_interval,
+            last: None,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoThrottleIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct MeteredIterator<I> {
+    iter: I,
+    count: usize,
+    start: Instant,
+    /// How long it took to run out, once it has
+    finished: Option<Duration>,
+}
+
+impl<I> MeteredIterator<I> {
+    /// The number of items yielded so far. This isn't called count, since Iterator::count would
+    /// be picked over it, consuming the iterator instead.
+    pub fn item_count(&self) -> usize {
+        self.count
+    }
+
+    /// Time since the iterator was created, up until it ran out.
+    pub fn elapsed(&self) -> Duration {
+        self.finished.unwrap_or_else(|| self.start.elapsed())
+    }
+
+    /// The average number of items yielded per second.
+    pub fn items_per_sec(&self) -> f64 {
+        self.count as f64 / self.elapsed().as_secs_f64()
+    }
+}
+
+impl<I: Iterator> Iterator for MeteredIterator<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.iter.next() {
+            Some(item) => {
+                self.count += 1;
+                Some(item)
+            }
+            None => {
+                self.finished.get_or_insert_with(|| self.start.elapsed());
+                None
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+pub trait IntoMeteredIterator: IntoIterator {
+    /// Counts the items yielded and the time taken, to measure the throughput of a pipeline.
+    fn metered(self) -> MeteredIterator<Self::IntoIter>
+    where
+        Self: Sized,
+    {
+        MeteredIterator {
+            iter: self.into_iter(),
+            count: 0,
+            start: Instant::now(),
+            finished: None,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoMeteredIterator for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::{IntoMeteredIterator, IntoThrottleIterator};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_throttle() {
+        let start = Instant::now();
+        let items = (0..3)
+            .throttle(Duration::from_millis(50))
+            .collect::<Vec<_>>();
+        assert_eq!(items, [0, 1, 2]);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_metered() {
+        let mut iter = (0..1000).map(|x| x * 2).metered();
+        let total = iter.by_ref().sum::<i32>();
+        assert_eq!(total, 999 * 1000);
+        assert_eq!(iter.item_count(), 1000);
+        assert!(iter.items_per_sec() > 0.0);
+
+        // The clock stops once it has run out
+        let elapsed = iter.elapsed();
+        thread::sleep(Duration::from_millis(5));
+        assert_eq!(iter.elapsed(), elapsed);
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone)]
+pub struct TupleWindowsIterator<I: Iterator> {
+    iter: I,
+    prev: Option<I::Item>,
+}
+
+impl<I> Iterator for TupleWindowsIterator<I>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    type Item = (I::Item, I::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let prev = match self.prev.take() {
+            Some(prev) => prev,
+            None => self.iter.next()?,
+        };
+        let next = self.iter.next()?;
+        self.prev = Some(next.clone());
+
+        Some((prev, next))
+    }
+}
+
+pub trait IntoTupleWindowsIterator: IntoIterator {
+    /// Yields each pair of adjacent items.
+    fn tuple_windows(self) -> TupleWindowsIterator<Self::IntoIter>
+    where
+        Self: Sized,
+        Self::Item: Clone,
+    {
+        TupleWindowsIterator {
+            iter: self.into_iter(),
+            prev: None,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoTupleWindowsIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct ContextWindowsIterator<I: Iterator> {
+    iter: I,
+    window: VecDeque<I::Item>,
+    context: usize,
+}
+
+impl<I> Iterator for ContextWindowsIterator<I>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        if self.window.len() > self.context {
+            self.window.pop_front();
+        }
+        self.window.push_back(item);
+
+        Some(self.window.iter().cloned().collect())
+    }
+}
+
+pub trait IntoContextWindowsIterator: IntoIterator {
+    /// Yields each item along with up to `context` items before it, oldest first.
+    /// The first few windows are shorter, since there's less context available.
+    fn context_windows(self, context: usize) -> ContextWindowsIterator<Self::IntoIter>
+    where
+        Self: Sized,
+        Self::Item: Clone,
+    {
+        ContextWindowsIterator {
+            iter: self.into_iter(),
+            window: VecDeque::with_capacity(context + 1),
+            context,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoContextWindowsIterator for I {}
+
+#[derive(Debug, Clone)]
+pub struct WithPreviousIterator<I: Iterator> {
+    iter: I,
+    prev: Option<I::Item>,
+}
+
+impl<I> Iterator for WithPreviousIterator<I>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    type Item = (Option<I::Item>, I::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.iter.next()?;
+        let prev = self.prev.replace(next.clone());
+
+        Some((prev, next))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+pub trait IntoWithPreviousIterator: IntoIterator {
+    /// Yields each item along with the one before it, which is None for the first item.
+    fn with_previous(self) -> WithPreviousIterator<Self::IntoIter>
+    where
+        Self: Sized,
+        Self::Item: Clone,
+    {
+        WithPreviousIterator {
+            iter: self.into_iter(),
+            prev: None,
+        }
+    }
+}
+
+impl<I: IntoIterator> IntoWithPreviousIterator for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::{IntoContextWindowsIterator, IntoTupleWindowsIterator, IntoWithPreviousIterator};
+
+    #[test]
+    fn test_tuple_windows() {
+        assert_eq!(Vec::<i32>::new().tuple_windows().count(), 0);
+        assert_eq!([1].tuple_windows().count(), 0);
+        assert_eq!([1, 2].tuple_windows().collect::<Vec<_>>(), [(1, 2)]);
+        assert_eq!(
+            [1, 2, 3].tuple_windows().collect::<Vec<_>>(),
+            [(1, 2), (2, 3)]
+        );
+    }
+
+    #[test]
+    fn test_context_windows() {
+        let windows = [1, 2, 3, 4].context_windows(2).collect::<Vec<_>>();
+        assert_eq!(windows, [vec![1], vec![1, 2], vec![1, 2, 3], vec![2, 3, 4]]);
+
+        let windows = [1, 2].context_windows(0).collect::<Vec<_>>();
+        assert_eq!(windows, [vec![1], vec![2]]);
+    }
+
+    #[test]
+    fn test_with_previous() {
+        let pairs = [10, 20, 30].with_previous().collect::<Vec<_>>();
+        assert_eq!(pairs, [(None, 10), (Some(10), 20), (Some(20), 30)]);
+
+        assert_eq!(Vec::<i32>::new().with_previous().count(), 0);
+    }
+}